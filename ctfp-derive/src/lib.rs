@@ -0,0 +1,383 @@
+// `#[derive(Semigroup, Monoid)]` for structs whose fields are all semigroups
+// (resp. monoids): `combine` and `empty` are done field by field, as for
+// tuples. Named, tuple and unit structs are supported, generic ones too (each
+// field type is then required to implement the trait).
+//
+// There's no syn/quote available, so the struct is picked apart by hand; it
+// only needs the generics, the where clause and the fields' names and types.
+// Field types are re-emitted w/ their own spans, so that a field which isn't
+// a monoid is what the compiler points at.
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+#[proc_macro_derive(Semigroup)]
+pub fn derive_semigroup(input: TokenStream) -> TokenStream {
+    expand(input, Derive::Semigroup)
+}
+
+#[proc_macro_derive(Monoid)]
+pub fn derive_monoid(input: TokenStream) -> TokenStream {
+    expand(input, Derive::Monoid)
+}
+
+#[derive(Clone, Copy)]
+enum Derive {
+    Semigroup,
+    Monoid,
+}
+
+impl Derive {
+    fn name(self) -> &'static str {
+        match self {
+            Derive::Semigroup => "Semigroup",
+            Derive::Monoid => "Monoid",
+        }
+    }
+
+    fn path(self) -> TokenStream {
+        code(&format!("::ctfp::monoid::{}", self.name()))
+    }
+}
+
+struct Struct {
+    name: Ident,
+    // params as declared, minus defaults, e.g. `'a, T: Clone, const N: usize`
+    impl_generics: Vec<TokenStream>,
+    // just the names, e.g. `'a, T, N`
+    ty_generics: Vec<TokenStream>,
+    where_clause: Vec<TokenTree>,
+    fields: Fields,
+}
+
+enum Fields {
+    Named(Vec<(Ident, TokenStream)>),
+    Unnamed(Vec<TokenStream>),
+    Unit,
+}
+
+impl Fields {
+    fn types(&self) -> Vec<&TokenStream> {
+        match self {
+            Fields::Named(fields) => fields.iter().map(|(_, ty)| ty).collect(),
+            Fields::Unnamed(types) => types.iter().collect(),
+            Fields::Unit => Vec::new(),
+        }
+    }
+}
+
+struct Error(Span, String);
+
+fn expand(input: TokenStream, derive: Derive) -> TokenStream {
+    match parse_struct(input, derive) {
+        Ok(s) => generate(&s, derive),
+        Err(Error(span, msg)) => compile_error(span, &msg),
+    }
+}
+
+// code -> tokens, for the boilerplate bits (spans are the derive's)
+fn code(s: &str) -> TokenStream {
+    s.parse().expect("generated invalid tokens")
+}
+
+fn group(delim: Delimiter, inner: TokenStream) -> TokenStream {
+    TokenTree::Group(Group::new(delim, inner)).into()
+}
+
+fn compile_error(span: Span, msg: &str) -> TokenStream {
+    let mut lit = Literal::string(msg);
+    lit.set_span(span);
+    let mut args = Group::new(Delimiter::Parenthesis, TokenTree::Literal(lit).into());
+    args.set_span(span);
+    let mut bang = Punct::new('!', Spacing::Alone);
+    bang.set_span(span);
+    let mut semi = Punct::new(';', Spacing::Alone);
+    semi.set_span(span);
+    [
+        TokenTree::Ident(Ident::new("compile_error", span)),
+        TokenTree::Punct(bang),
+        TokenTree::Group(args),
+        TokenTree::Punct(semi),
+    ]
+    .into_iter()
+    .collect()
+}
+
+fn is_punct(tt: &TokenTree, c: char) -> bool {
+    matches!(tt, TokenTree::Punct(p) if p.as_char() == c)
+}
+
+fn is_ident(tt: &TokenTree, s: &str) -> bool {
+    matches!(tt, TokenTree::Ident(i) if i.to_string() == s)
+}
+
+// split at the commas outside of any `<...>`; groups are single tokens, so
+// only angle brackets need tracking (w/ care for the `>` of `->`)
+fn split_top_level(tokens: Vec<TokenTree>, sep: char) -> Vec<Vec<TokenTree>> {
+    let mut parts = vec![Vec::new()];
+    let mut depth = 0usize;
+    let mut after_dash = false;
+    for tt in tokens {
+        let dash = is_punct(&tt, '-');
+        if is_punct(&tt, '<') {
+            depth += 1;
+        } else if is_punct(&tt, '>') && !after_dash {
+            depth = depth.saturating_sub(1);
+        } else if depth == 0 && is_punct(&tt, sep) {
+            parts.push(Vec::new());
+            after_dash = false;
+            continue;
+        }
+        after_dash = dash;
+        parts.last_mut().unwrap().push(tt);
+    }
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+// drops leading `#[...]` attributes and `pub`/`pub(...)`
+fn skip_attrs_and_vis(tokens: &[TokenTree]) -> &[TokenTree] {
+    let mut rest = tokens;
+    loop {
+        match rest {
+            [hash, TokenTree::Group(_), tail @ ..] if is_punct(hash, '#') => rest = tail,
+            [pub_, TokenTree::Group(g), tail @ ..]
+                if is_ident(pub_, "pub") && g.delimiter() == Delimiter::Parenthesis =>
+            {
+                rest = tail
+            }
+            [pub_, tail @ ..] if is_ident(pub_, "pub") => rest = tail,
+            _ => return rest,
+        }
+    }
+}
+
+fn parse_struct(input: TokenStream, derive: Derive) -> Result<Struct, Error> {
+    let all: Vec<TokenTree> = input.into_iter().collect();
+    let mut tokens = skip_attrs_and_vis(&all).iter().cloned().peekable();
+
+    match tokens.next() {
+        Some(TokenTree::Ident(kw)) if kw.to_string() == "struct" => {}
+        Some(tt) => {
+            return Err(Error(
+                tt.span(),
+                format!("`{}` can only be derived for structs", derive.name()),
+            ))
+        }
+        None => return Err(Error(Span::call_site(), "expected a struct".into())),
+    }
+    let name = match tokens.next() {
+        Some(TokenTree::Ident(name)) => name,
+        _ => {
+            return Err(Error(
+                Span::call_site(),
+                "expected the struct's name".into(),
+            ))
+        }
+    };
+
+    let mut generics = Vec::new();
+    if tokens.peek().is_some_and(|tt| is_punct(tt, '<')) {
+        tokens.next();
+        let mut depth = 1;
+        let mut after_dash = false;
+        for tt in tokens.by_ref() {
+            if is_punct(&tt, '<') {
+                depth += 1;
+            } else if is_punct(&tt, '>') && !after_dash {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            after_dash = is_punct(&tt, '-');
+            generics.push(tt);
+        }
+    }
+    let (impl_generics, ty_generics) = split_generics(generics);
+
+    let mut where_clause = Vec::new();
+    let mut fields = Fields::Unit;
+    let mut in_where = false;
+    for tt in tokens {
+        match &tt {
+            TokenTree::Ident(i) if i.to_string() == "where" => in_where = true,
+            TokenTree::Group(g) if g.delimiter() == Delimiter::Brace => {
+                fields = Fields::Named(parse_named_fields(g.stream())?);
+                in_where = false;
+            }
+            TokenTree::Group(g) if g.delimiter() == Delimiter::Parenthesis && !in_where => {
+                fields = Fields::Unnamed(parse_unnamed_fields(g.stream()));
+            }
+            TokenTree::Punct(p) if p.as_char() == ';' => break,
+            _ if in_where => where_clause.push(tt),
+            _ => {}
+        }
+    }
+
+    Ok(Struct {
+        name,
+        impl_generics,
+        ty_generics,
+        where_clause,
+        fields,
+    })
+}
+
+fn split_generics(generics: Vec<TokenTree>) -> (Vec<TokenStream>, Vec<TokenStream>) {
+    let mut impl_generics = Vec::new();
+    let mut ty_generics = Vec::new();
+    for param in split_top_level(generics, ',') {
+        // drop any default (`= ...`)
+        let decl = split_top_level(param.clone(), '=').swap_remove(0);
+        let name: TokenStream = match param.as_slice() {
+            [quote, lifetime, ..] if is_punct(quote, '\'') => {
+                [quote.clone(), lifetime.clone()].into_iter().collect()
+            }
+            [const_, name, ..] if is_ident(const_, "const") => name.clone().into(),
+            [name, ..] => name.clone().into(),
+            [] => continue,
+        };
+        impl_generics.push(decl.into_iter().collect());
+        ty_generics.push(name);
+    }
+    (impl_generics, ty_generics)
+}
+
+fn parse_named_fields(body: TokenStream) -> Result<Vec<(Ident, TokenStream)>, Error> {
+    split_top_level(body.into_iter().collect(), ',')
+        .into_iter()
+        .map(|field| match skip_attrs_and_vis(&field) {
+            [TokenTree::Ident(name), colon, ty @ ..] if is_punct(colon, ':') => {
+                Ok((name.clone(), ty.iter().cloned().collect()))
+            }
+            _ => Err(Error(field[0].span(), "expected `name: Type`".into())),
+        })
+        .collect()
+}
+
+fn parse_unnamed_fields(body: TokenStream) -> Vec<TokenStream> {
+    split_top_level(body.into_iter().collect(), ',')
+        .iter()
+        .map(|field| skip_attrs_and_vis(field).iter().cloned().collect())
+        .collect()
+}
+
+fn comma_separated(items: impl IntoIterator<Item = TokenStream>) -> TokenStream {
+    let mut out = TokenStream::new();
+    for item in items {
+        out.extend(item);
+        out.extend(code(","));
+    }
+    out
+}
+
+// `<Ty as ::ctfp::monoid::Trait>::method`
+fn qualified(ty: &TokenStream, derive: Derive, method: &str) -> TokenStream {
+    let mut out = code("<");
+    out.extend(ty.clone());
+    out.extend(code("as"));
+    out.extend(derive.path());
+    out.extend(code(&format!(">::{}", method)));
+    out
+}
+
+fn generate(s: &Struct, derive: Derive) -> TokenStream {
+    // `impl<..> Trait for Name<..> where ..`
+    let mut out = code("impl");
+    if !s.impl_generics.is_empty() {
+        out.extend(code("<"));
+        out.extend(comma_separated(s.impl_generics.iter().cloned()));
+        out.extend(code(">"));
+    }
+    out.extend(derive.path());
+    out.extend(code("for"));
+    out.extend(TokenStream::from(TokenTree::Ident(s.name.clone())));
+    if !s.ty_generics.is_empty() {
+        out.extend(code("<"));
+        out.extend(comma_separated(s.ty_generics.iter().cloned()));
+        out.extend(code(">"));
+    }
+
+    // a concrete field type that isn't a monoid is better reported in the
+    // body than as an unsatisfiable bound, so only generic structs get them
+    let mut preds: Vec<TokenStream> = Vec::new();
+    if !s.where_clause.is_empty() {
+        preds.extend(
+            split_top_level(s.where_clause.clone(), ',')
+                .into_iter()
+                .map(TokenStream::from_iter),
+        );
+    }
+    if !s.ty_generics.is_empty() {
+        for ty in s.fields.types() {
+            let mut pred = ty.clone();
+            pred.extend(code(":"));
+            pred.extend(derive.path());
+            preds.push(pred);
+        }
+    }
+    if !preds.is_empty() {
+        out.extend(code("where"));
+        out.extend(comma_separated(preds));
+    }
+
+    let method = match derive {
+        Derive::Semigroup => {
+            let combine = |ty: &TokenStream, field: &str| {
+                let mut call = qualified(ty, derive, "combine");
+                call.extend(group(
+                    Delimiter::Parenthesis,
+                    code(&format!("self.{}, other.{}", field, field)),
+                ));
+                call
+            };
+            // a unit struct doesn't look at `other`
+            let other = match s.fields {
+                Fields::Unit => "_",
+                _ => "other",
+            };
+            let mut sig = code(&format!("fn combine(self, {}: Self) -> Self", other));
+            sig.extend(group(Delimiter::Brace, construct(&s.fields, combine)));
+            sig
+        }
+        Derive::Monoid => {
+            let empty = |ty: &TokenStream, _: &str| {
+                let mut call = qualified(ty, derive, "empty");
+                call.extend(code("()"));
+                call
+            };
+            let mut sig = code("fn empty() -> Self");
+            sig.extend(group(Delimiter::Brace, construct(&s.fields, empty)));
+            sig
+        }
+    };
+    out.extend(group(Delimiter::Brace, method));
+    out
+}
+
+// `Self { a: value(Ta, "a"), .. }` or `Self(value(T0, "0"), ..)`
+fn construct(fields: &Fields, value: impl Fn(&TokenStream, &str) -> TokenStream) -> TokenStream {
+    let mut out = code("Self");
+    match fields {
+        Fields::Named(fields) => {
+            let inits = fields.iter().map(|(name, ty)| {
+                let mut init = TokenStream::from(TokenTree::Ident(name.clone()));
+                init.extend(code(":"));
+                init.extend(value(ty, &name.to_string()));
+                init
+            });
+            out.extend(group(Delimiter::Brace, comma_separated(inits)));
+        }
+        Fields::Unnamed(types) => {
+            let values = types
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| value(ty, &i.to_string()));
+            out.extend(group(Delimiter::Parenthesis, comma_separated(values)));
+        }
+        Fields::Unit => {}
+    }
+    out
+}
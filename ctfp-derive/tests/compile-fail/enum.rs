@@ -0,0 +1,10 @@
+// error: `Semigroup` can only be derived for structs
+use ctfp::monoid::Semigroup;
+
+#[derive(Semigroup)]
+enum Status {
+    Up,
+    Down,
+}
+
+fn main() {}
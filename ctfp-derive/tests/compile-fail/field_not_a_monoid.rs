@@ -0,0 +1,11 @@
+// error: the trait bound `First<u8>: Monoid` is not satisfied
+use ctfp::monoid::{First, Monoid, Semigroup, Sum};
+
+// `First` is only a semigroup: there's no first element of nothing
+#[derive(Semigroup, Monoid)]
+struct Metrics {
+    requests: Sum<u64>,
+    first_status: First<u8>,
+}
+
+fn main() {}
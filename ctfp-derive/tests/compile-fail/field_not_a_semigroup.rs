@@ -0,0 +1,10 @@
+// error: the trait bound `u32: Semigroup` is not satisfied
+use ctfp::monoid::{Semigroup, Sum};
+
+#[derive(Semigroup)]
+struct Metrics {
+    requests: Sum<u64>,
+    slowest: u32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+// error: trait bound `bool: Semigroup` was not satisfied
+use ctfp::monoid::{Semigroup, Sum};
+
+#[derive(Semigroup)]
+struct Pair<A, B>(A, B);
+
+fn main() {
+    let _ = Pair(Sum(1), true).combine(Pair(Sum(2), false));
+}
@@ -0,0 +1,9 @@
+// error: the trait bound `Metrics: Semigroup` is not satisfied
+use ctfp::monoid::{Monoid, Sum};
+
+#[derive(Monoid)]
+struct Metrics {
+    requests: Sum<u64>,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use ctfp::monoid::{Max, Monoid, Semigroup, Sum};
+
+#[derive(Semigroup, Monoid)]
+struct Metrics {
+    requests: Sum<u64>,
+    slowest: Max<u32>,
+}
+
+fn main() {
+    let m = Metrics::empty().combine(Metrics {
+        requests: Sum(1),
+        slowest: Max(3),
+    });
+    assert_eq!((m.requests, m.slowest), (Sum(1), Max(3)));
+}
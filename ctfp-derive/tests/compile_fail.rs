@@ -0,0 +1,93 @@
+// Compile-fail tests for the derives, w/o trybuild: each fixture is checked as
+// the main.rs of a scratch crate depending on ctfp. Those under compile-fail/
+// must fail w/ the error given on their first line (`// error: ...`), those
+// under compile-pass/ must build, which also shows the scratch crate is sound.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn fixtures(dir: &str) -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(dir);
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+// a crate under the workspace's target dir, w/ its own target dir so as not to
+// contend w/ the outer build's lock
+fn scratch_crate() -> PathBuf {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let dir = root.join("target").join("derive-compile-fail");
+    fs::create_dir_all(dir.join("src")).unwrap();
+    let manifest = format!(
+        concat!(
+            "[package]\n",
+            "name = \"derive-compile-fail\"\n",
+            "version = \"0.0.0\"\n",
+            "edition = \"2021\"\n\n",
+            "[dependencies]\n",
+            "ctfp-challenges = {{ path = {:?} }}\n\n",
+            // not part of the outer workspace
+            "[workspace]\n",
+        ),
+        root
+    );
+    fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+    dir
+}
+
+// stderr if the fixture fails to build
+fn check(krate: &Path, fixture: &Path) -> Result<(), String> {
+    fs::copy(fixture, krate.join("src").join("main.rs")).unwrap();
+    let out = Command::new(env!("CARGO"))
+        .args(["check", "--quiet", "--offline", "--color", "never"])
+        .current_dir(krate)
+        .env("CARGO_TARGET_DIR", krate.join("target"))
+        .output()
+        .unwrap();
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&out.stderr).into_owned())
+    }
+}
+
+#[test]
+fn test_derives() {
+    let krate = scratch_crate();
+    let mut failures = Vec::new();
+
+    for fixture in fixtures("compile-pass") {
+        if let Err(stderr) = check(&krate, &fixture) {
+            failures.push(format!("{} didn't build:\n{}", fixture.display(), stderr));
+        }
+    }
+
+    for fixture in fixtures("compile-fail") {
+        let source = fs::read_to_string(&fixture).unwrap();
+        let expected = source
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("// error: "))
+            .unwrap_or_else(|| panic!("{} is missing its `// error:` line", fixture.display()));
+        match check(&krate, &fixture) {
+            Ok(()) => failures.push(format!("{} built", fixture.display())),
+            Err(stderr) if !stderr.contains(expected) => failures.push(format!(
+                "{} didn't fail w/ `{}`:\n{}",
+                fixture.display(),
+                expected,
+                stderr
+            )),
+            Err(_) => {}
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
@@ -0,0 +1,538 @@
+// QuickCheck-style value generation w/o external crates, for checking laws
+// over many inputs rather than a few hand-picked literals.
+//
+// Generation is driven by a seeded SplitMix64 PRNG so runs are reproducible,
+// and failing inputs are shrunk towards a minimal counterexample.
+// Numbers are bounded by the generator's `size` (and floats are always
+// finite) so that laws over e.g. `x + 1` or `==` don't trip over overflow
+// or NaN.
+
+#![allow(unused)]
+
+use std::fmt::Debug;
+
+use crate::ch5::Either;
+use crate::ch6::Shape;
+
+pub struct Gen {
+    state: u64,
+    size: usize,
+}
+
+impl Gen {
+    pub const DEFAULT_SIZE: usize = 100;
+
+    pub fn new(seed: u64) -> Self {
+        Self::with_size(seed, Self::DEFAULT_SIZE)
+    }
+
+    // `size` bounds collection lengths and numeric magnitudes
+    pub fn with_size(seed: u64, size: usize) -> Self {
+        Self { state: seed, size }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    // SplitMix64
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, n); n must be non-zero
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    // uniform in [lo, hi]
+    pub fn range_i128(&mut self, lo: i128, hi: i128) -> i128 {
+        let span = (hi - lo) as u128 + 1;
+        lo + ((self.next_u64() as u128) % span) as i128
+    }
+
+    // uniform in [0, 1)
+    pub fn unit_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn coin(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+
+    // a collection length in [0, size]
+    pub fn collection_len(&mut self) -> usize {
+        self.below(self.size as u64 + 1) as usize
+    }
+}
+
+pub trait Arbitrary: Clone + Debug + Sized + 'static {
+    fn arbitrary(g: &mut Gen) -> Self;
+
+    // strictly "smaller" candidates, most aggressive first
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+// `n` reproducible values, e.g. as samples for the law checkers
+pub fn samples<T: Arbitrary>(seed: u64, n: usize) -> Vec<T> {
+    let mut g = Gen::new(seed);
+    (0..n).map(|_| T::arbitrary(&mut g)).collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counterexample<T> {
+    pub seed: u64,
+    // number of inputs that passed before the failure
+    pub passed: usize,
+    pub original: T,
+    pub shrunk: T,
+    pub shrink_steps: usize,
+}
+
+// upper bound on successful shrink steps, in case a shrinker doesn't converge
+const MAX_SHRINK_STEPS: usize = 1000;
+
+// check `prop` on `n` generated inputs; on failure, greedily shrink the input
+// as long as some smaller candidate still fails
+pub fn for_all<T: Arbitrary>(
+    seed: u64,
+    n: usize,
+    prop: impl Fn(T) -> bool,
+) -> Result<(), Counterexample<T>> {
+    let mut g = Gen::new(seed);
+    for passed in 0..n {
+        let x = T::arbitrary(&mut g);
+        if !prop(x.clone()) {
+            let (shrunk, shrink_steps) = shrink_failure(x.clone(), &prop);
+            return Err(Counterexample {
+                seed,
+                passed,
+                original: x,
+                shrunk,
+                shrink_steps,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn shrink_failure<T: Arbitrary>(mut x: T, prop: &impl Fn(T) -> bool) -> (T, usize) {
+    let mut steps = 0;
+    while steps < MAX_SHRINK_STEPS {
+        match x.shrink().find(|c| !prop(c.clone())) {
+            Some(smaller) => {
+                x = smaller;
+                steps += 1;
+            }
+            None => break,
+        }
+    }
+    (x, steps)
+}
+
+// 0, x/2, 3x/4, ..., x - 1 (towards zero from either side)
+fn shrink_integer(x: i128) -> impl Iterator<Item = i128> {
+    let neg = (x < 0).then_some(-x);
+    let towards_zero = std::iter::successors((x != 0).then_some(x), |d| {
+        let d = d / 2;
+        (d != 0).then_some(d)
+    })
+    .map(move |d| x - d);
+    neg.into_iter().chain(towards_zero)
+}
+
+macro_rules! arbitrary_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Arbitrary for $t {
+                fn arbitrary(g: &mut Gen) -> Self {
+                    let size = g.size() as i128;
+                    let lo = (<$t>::MIN as i128).max(-size);
+                    let hi = (<$t>::MAX as i128).min(size);
+                    g.range_i128(lo, hi) as $t
+                }
+
+                fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+                    Box::new(shrink_integer(*self as i128).filter_map(|x| <$t>::try_from(x).ok()))
+                }
+            }
+        )*
+    };
+}
+
+arbitrary_integer!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+macro_rules! arbitrary_float {
+    ($($t:ty),*) => {
+        $(
+            impl Arbitrary for $t {
+                fn arbitrary(g: &mut Gen) -> Self {
+                    let size = g.size() as f64;
+                    ((g.unit_f64() * 2.0 - 1.0) * size) as $t
+                }
+
+                // 0, then drop the fractional part, then halve
+                fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+                    let x = *self;
+                    let candidates = [0.0, x.trunc(), x / 2.0];
+                    Box::new(
+                        candidates
+                            .into_iter()
+                            .filter(move |c| c.abs() < x.abs())
+                            .fold(Vec::new(), |mut acc, c| {
+                                if !acc.contains(&c) {
+                                    acc.push(c);
+                                }
+                                acc
+                            })
+                            .into_iter(),
+                    )
+                }
+            }
+        )*
+    };
+}
+
+arbitrary_float!(f32, f64);
+
+impl Arbitrary for bool {
+    fn arbitrary(g: &mut Gen) -> Self {
+        g.coin()
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        Box::new(self.then_some(false).into_iter())
+    }
+}
+
+impl Arbitrary for char {
+    // printable ASCII, w/ the occasional non-ASCII char
+    fn arbitrary(g: &mut Gen) -> Self {
+        if g.below(8) == 0 {
+            ['é', 'λ', '∘', '→', '🦀'][g.below(5) as usize]
+        } else {
+            (b' ' + g.below(95) as u8) as char
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let c = *self;
+        Box::new(['a', 'b', ' '].into_iter().filter(move |&s| s < c))
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Vec<T> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let n = g.collection_len();
+        (0..n).map(|_| T::arbitrary(g)).collect()
+    }
+
+    // drop chunks (halves, quarters, ... single elements), then shrink
+    // elements one at a time
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let xs = self.clone();
+        let n = xs.len();
+        let chunk_sizes = std::iter::successors((n > 0).then_some(n), |k| {
+            let k = k / 2;
+            (k > 0).then_some(k)
+        });
+        let removals = {
+            let xs = xs.clone();
+            chunk_sizes.flat_map(move |k| {
+                let xs = xs.clone();
+                (0..n).step_by(k).map(move |start| {
+                    let mut ys = xs.clone();
+                    ys.drain(start..(start + k).min(n));
+                    ys
+                })
+            })
+        };
+        let elementwise = (0..n).flat_map(move |i| {
+            let xs = xs.clone();
+            xs[i].shrink().map(move |x| {
+                let mut ys = xs.clone();
+                ys[i] = x;
+                ys
+            })
+        });
+        Box::new(removals.chain(elementwise))
+    }
+}
+
+impl Arbitrary for String {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Vec::<char>::arbitrary(g).into_iter().collect()
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let chars: Vec<char> = self.chars().collect();
+        Box::new(chars.shrink().map(|cs| cs.into_iter().collect()))
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Option<T> {
+    // None about one time in four
+    fn arbitrary(g: &mut Gen) -> Self {
+        (g.below(4) != 0).then(|| T::arbitrary(g))
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        match self {
+            None => Box::new(std::iter::empty()),
+            Some(x) => Box::new(std::iter::once(None).chain(x.shrink().map(Some))),
+        }
+    }
+}
+
+impl<T: Arbitrary, E: Arbitrary> Arbitrary for Result<T, E> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        if g.below(4) != 0 {
+            Ok(T::arbitrary(g))
+        } else {
+            Err(E::arbitrary(g))
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        match self {
+            Ok(x) => Box::new(x.shrink().map(Ok)),
+            Err(e) => Box::new(e.shrink().map(Err)),
+        }
+    }
+}
+
+impl<T: Arbitrary, U: Arbitrary> Arbitrary for Either<T, U> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        if g.coin() {
+            Either::Left(T::arbitrary(g))
+        } else {
+            Either::Right(U::arbitrary(g))
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        match self {
+            Either::Left(t) => Box::new(t.shrink().map(Either::Left)),
+            Either::Right(u) => Box::new(u.shrink().map(Either::Right)),
+        }
+    }
+}
+
+impl Arbitrary for () {
+    fn arbitrary(_: &mut Gen) -> Self {}
+}
+
+macro_rules! arbitrary_tuple {
+    ($(($($t:ident $i:tt),+)),*) => {
+        $(
+            impl<$($t: Arbitrary),+> Arbitrary for ($($t,)+) {
+                fn arbitrary(g: &mut Gen) -> Self {
+                    ($($t::arbitrary(g),)+)
+                }
+
+                // shrink one component at a time
+                fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+                    let mut candidates: Vec<Self> = Vec::new();
+                    $(
+                        candidates.extend(self.$i.shrink().map(|x| {
+                            let mut t = self.clone();
+                            t.$i = x;
+                            t
+                        }));
+                    )+
+                    Box::new(candidates.into_iter())
+                }
+            }
+        )*
+    };
+}
+
+arbitrary_tuple!(
+    (A 0),
+    (A 0, B 1),
+    (A 0, B 1, C 2),
+    (A 0, B 1, C 2, D 3)
+);
+
+// a positive dimension, so that every generated shape is a valid one
+fn arbitrary_dim(g: &mut Gen) -> f64 {
+    f64::arbitrary(g).abs().max(f64::EPSILON)
+}
+
+fn shrink_dim(d: f64) -> impl Iterator<Item = f64> {
+    [1.0, d.trunc(), d / 2.0]
+        .into_iter()
+        .filter(move |&c| c > 0.0 && c < d)
+}
+
+impl Arbitrary for Shape {
+    fn arbitrary(g: &mut Gen) -> Self {
+        match g.below(3) {
+            0 => Shape::Circle {
+                r: arbitrary_dim(g),
+            },
+            1 => Shape::Rectangle {
+                d: arbitrary_dim(g),
+                h: arbitrary_dim(g),
+            },
+            _ => Shape::Square {
+                s: arbitrary_dim(g),
+            },
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        match *self {
+            Shape::Circle { r } => Box::new(shrink_dim(r).map(|r| Shape::Circle { r })),
+            Shape::Square { s } => Box::new(shrink_dim(s).map(|s| Shape::Square { s })),
+            Shape::Rectangle { d, h } => Box::new(
+                // a rectangle w/ equal sides is a square
+                std::iter::once(Shape::Square { s: d })
+                    .chain(shrink_dim(d).map(move |d| Shape::Rectangle { d, h }))
+                    .chain(shrink_dim(h).map(move |h| Shape::Rectangle { d, h })),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::categories::FnCat;
+    use crate::category_laws::check_category_laws;
+    use crate::ch1::id;
+    use crate::ch3::{Monoid, Semigroup, Sum};
+    use crate::ch7::Functor;
+    use crate::ch8::Bifunctor;
+
+    #[test]
+    fn test_generation_is_reproducible() {
+        let a: Vec<(u32, String, Option<f64>)> = samples(42, 50);
+        let b: Vec<(u32, String, Option<f64>)> = samples(42, 50);
+        let c: Vec<(u32, String, Option<f64>)> = samples(43, 50);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_generated_values_respect_size() {
+        let mut g = Gen::with_size(7, 10);
+        for _ in 0..500 {
+            assert!(i32::arbitrary(&mut g).abs() <= 10);
+            assert!(f64::arbitrary(&mut g).abs() <= 10.0);
+            assert!(Vec::<u8>::arbitrary(&mut g).len() <= 10);
+            assert!(Shape::arbitrary(&mut g).area() > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_shrink_integer_to_boundary() {
+        let cex = for_all(1, 500, |x: u32| x < 50).unwrap_err();
+        assert!(cex.original >= 50);
+        assert_eq!(cex.shrunk, 50);
+
+        let cex = for_all(1, 500, |x: i64| x > -7).unwrap_err();
+        assert_eq!(cex.shrunk, -7);
+    }
+
+    #[test]
+    fn test_shrink_vec_to_minimal() {
+        let cex = for_all(3, 500, |xs: Vec<u8>| xs.len() < 3).unwrap_err();
+        assert_eq!(cex.shrunk, vec![0, 0, 0]);
+
+        let cex = for_all(3, 500, |xs: Vec<u8>| !xs.contains(&9)).unwrap_err();
+        assert_eq!(cex.shrunk, vec![9]);
+    }
+
+    #[test]
+    fn test_shrink_compound_values() {
+        let cex = for_all(5, 500, |s: String| !s.contains('x')).unwrap_err();
+        assert_eq!(cex.shrunk, "x");
+
+        let cex = for_all(5, 500, |e: Either<u8, Option<u8>>| match e {
+            Either::Right(Some(x)) => x < 10,
+            _ => true,
+        })
+        .unwrap_err();
+        assert_eq!(cex.shrunk, Either::Right(Some(10)));
+
+        let cex = for_all(5, 500, |s: Shape| s.perim() < 40.0).unwrap_err();
+        assert!(matches!(
+            cex.shrunk,
+            Shape::Square { .. } | Shape::Circle { .. }
+        ));
+        assert!(cex.shrunk.perim() >= 40.0);
+    }
+
+    #[test]
+    fn test_for_all_passes() {
+        assert_eq!(for_all(0, 500, |(a, b): (i32, i32)| a + b == b + a), Ok(()));
+        assert_eq!(
+            for_all(0, 500, |r: Result<bool, String>| r.is_ok() || r.is_err()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_category_laws_over_samples() {
+        let result = check_category_laws::<FnCat, _, _, _, _>(
+            || Box::new(|x: i64| x * 3),
+            || Box::new(|x: i64| x.to_string()),
+            || Box::new(|s: String| s.len()),
+            samples::<i64>(7, 500),
+        );
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_functor_laws_for_option() {
+        let f = |x: i32| x * 2;
+        let g = |x: i32| x - 1;
+        assert_eq!(
+            for_all(11, 500, |o: Option<i32>| o.fmap(id) == id(o)),
+            Ok(())
+        );
+        assert_eq!(
+            for_all(11, 500, |o: Option<i32>| o.fmap(|x| g(f(x)))
+                == o.fmap(f).fmap(g)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_bifunctor_laws_for_either() {
+        let f = |x: u8| x as u32 + 1;
+        let g = |s: String| s.len();
+        assert_eq!(
+            for_all(13, 500, |e: Either<u8, String>| e.clone().bimap(id, id)
+                == e),
+            Ok(())
+        );
+        assert_eq!(
+            for_all(13, 500, |e: Either<u8, String>| {
+                e.clone().bimap(f, g) == e.first(f).second(g)
+            }),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_monoid_laws_for_sum() {
+        assert_eq!(
+            for_all(17, 500, |(a, b, c): (u32, u32, u32)| {
+                Sum(a).combine(Sum(b)).combine(Sum(c)) == Sum(a).combine(Sum(b).combine(Sum(c)))
+            }),
+            Ok(())
+        );
+        assert_eq!(
+            for_all(17, 500, |a: u32| Sum::empty().combine(Sum(a)) == Sum(a)),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,283 @@
+// Small benchmark harness, replacing the copy-pasted `Instant` loops the
+// memoizer modules used to have, in lieu of using either Unstable's
+// test::Benchmark or Criterion.
+//
+// Each benchmark runs some warmup iterations, then times `samples` single
+// iterations and summarises them (mean, median, percentiles, outliers by
+// Tukey's fences). Reports are one JSON object per line, printed and, when
+// `CTFP_BENCH_REPORT` names a file, also appended to it, so that runs can be
+// compared across commits.
+//
+// `bench!` wraps all that into an ignored test, reported under its full path:
+//   run w/ `cargo test --release -- --ignored --show-output bench_`
+
+#![allow(unused)]
+
+use std::fs::OpenOptions;
+use std::hint::black_box;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+pub const REPORT_ENV_VAR: &str = "CTFP_BENCH_REPORT";
+
+// `s` as a quoted JSON string, w/ quotes, backslashes and control characters
+// escaped
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchConfig {
+    pub warmup: usize,
+    pub samples: usize,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            warmup: 100,
+            samples: 1000,
+        }
+    }
+}
+
+impl BenchConfig {
+    pub fn warmup(self, warmup: usize) -> Self {
+        Self { warmup, ..self }
+    }
+
+    pub fn samples(self, samples: usize) -> Self {
+        assert!(samples > 0, "need at least one sample");
+        Self { samples, ..self }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    pub name: String,
+    pub warmup: usize,
+    pub samples: usize,
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub std_dev: Duration,
+    pub median: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    // samples outside of the inner fences (1.5 IQR beyond the quartiles)
+    pub low_outliers: usize,
+    pub high_outliers: usize,
+}
+
+// nearest-rank percentile of sorted samples
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl BenchReport {
+    pub fn from_samples(name: &str, warmup: usize, mut samples: Vec<Duration>) -> Self {
+        assert!(!samples.is_empty(), "need at least one sample");
+        samples.sort();
+        let n = samples.len();
+
+        let secs: Vec<f64> = samples.iter().map(Duration::as_secs_f64).collect();
+        let mean = secs.iter().sum::<f64>() / n as f64;
+        let var = secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n as f64;
+
+        let (q1, q3) = (percentile(&samples, 25.0), percentile(&samples, 75.0));
+        let iqr = q3 - q1;
+        let low_fence = q1.saturating_sub(iqr * 3 / 2);
+        let high_fence = q3 + iqr * 3 / 2;
+
+        Self {
+            name: name.to_string(),
+            warmup,
+            samples: n,
+            min: samples[0],
+            max: samples[n - 1],
+            mean: Duration::from_secs_f64(mean),
+            std_dev: Duration::from_secs_f64(var.sqrt()),
+            median: percentile(&samples, 50.0),
+            p90: percentile(&samples, 90.0),
+            p99: percentile(&samples, 99.0),
+            low_outliers: samples.iter().filter(|&&s| s < low_fence).count(),
+            high_outliers: samples.iter().filter(|&&s| s > high_fence).count(),
+        }
+    }
+
+    // one line of JSON, durations in nanoseconds
+    pub fn to_json(&self) -> String {
+        format!(
+            concat!(
+                r#"{{"name":{},"warmup":{},"samples":{},"min_ns":{},"max_ns":{},"#,
+                r#""mean_ns":{},"std_dev_ns":{},"median_ns":{},"p90_ns":{},"p99_ns":{},"#,
+                r#""low_outliers":{},"high_outliers":{}}}"#
+            ),
+            json_string(&self.name),
+            self.warmup,
+            self.samples,
+            self.min.as_nanos(),
+            self.max.as_nanos(),
+            self.mean.as_nanos(),
+            self.std_dev.as_nanos(),
+            self.median.as_nanos(),
+            self.p90.as_nanos(),
+            self.p99.as_nanos(),
+            self.low_outliers,
+            self.high_outliers,
+        )
+    }
+
+    // print the report, and append it to `$CTFP_BENCH_REPORT` if set
+    pub fn emit(&self) -> io::Result<()> {
+        let line = self.to_json();
+        println!("{}", line);
+        if let Some(path) = std::env::var_os(REPORT_ENV_VAR) {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+// `routine` gets the state built by `setup` (once, before warming up), so
+// that e.g. a memoizer's cache carries over from one iteration to the next
+pub fn run_with_state<S, R>(
+    name: &str,
+    config: &BenchConfig,
+    mut state: S,
+    mut routine: impl FnMut(&mut S) -> R,
+) -> BenchReport {
+    for _ in 0..config.warmup {
+        black_box(routine(&mut state));
+    }
+    let samples = (0..config.samples)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(routine(&mut state));
+            start.elapsed()
+        })
+        .collect();
+    BenchReport::from_samples(name, config.warmup, samples)
+}
+
+pub fn run<R>(name: &str, config: &BenchConfig, mut routine: impl FnMut() -> R) -> BenchReport {
+    run_with_state(name, config, (), |_| routine())
+}
+
+// define an ignored test benchmarking a block:
+//   bench!(bench_name, config, { ... });
+// or w/ state that persists across iterations:
+//   bench!(bench_name, config, setup_expr => |state| { ... });
+#[macro_export]
+macro_rules! bench {
+    ($name:ident, $config:expr, $setup:expr => |$state:ident| $body:block) => {
+        #[ignore]
+        #[test]
+        fn $name() {
+            let name = concat!(module_path!(), "::", stringify!($name));
+            let report = $crate::bench::run_with_state(name, &$config, $setup, |$state| $body);
+            report.emit().unwrap();
+        }
+    };
+    ($name:ident, $config:expr, $body:block) => {
+        #[ignore]
+        #[test]
+        fn $name() {
+            let name = concat!(module_path!(), "::", stringify!($name));
+            let report = $crate::bench::run(name, &$config, || $body);
+            report.emit().unwrap();
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn micros(xs: &[u64]) -> Vec<Duration> {
+        xs.iter().map(|&x| Duration::from_micros(x)).collect()
+    }
+
+    #[test]
+    fn test_report_statistics() {
+        let report = BenchReport::from_samples("stats", 0, micros(&[5, 1, 4, 2, 3]));
+        assert_eq!(report.samples, 5);
+        assert_eq!(report.min, Duration::from_micros(1));
+        assert_eq!(report.max, Duration::from_micros(5));
+        assert_eq!(report.mean, Duration::from_micros(3));
+        assert_eq!(report.median, Duration::from_micros(3));
+        assert_eq!(report.p90, Duration::from_micros(5));
+        assert_eq!(report.std_dev.as_nanos(), 1414);
+        assert_eq!((report.low_outliers, report.high_outliers), (0, 0));
+    }
+
+    #[test]
+    fn test_percentiles_nearest_rank() {
+        let samples = micros(&(1..=100).collect::<Vec<_>>());
+        assert_eq!(percentile(&samples, 0.0), Duration::from_micros(1));
+        assert_eq!(percentile(&samples, 50.0), Duration::from_micros(50));
+        assert_eq!(percentile(&samples, 99.0), Duration::from_micros(99));
+        assert_eq!(percentile(&samples, 100.0), Duration::from_micros(100));
+    }
+
+    #[test]
+    fn test_outlier_detection() {
+        // quartiles 10 and 12, so the fences are 7 and 15
+        let mut xs = vec![10; 10];
+        xs.extend([11; 10]);
+        xs.extend([12; 10]);
+        xs.extend([1, 100, 200]);
+        let report = BenchReport::from_samples("outliers", 0, micros(&xs));
+        assert_eq!((report.low_outliers, report.high_outliers), (1, 2));
+    }
+
+    #[test]
+    fn test_json_report() {
+        let report = BenchReport::from_samples(r#"a "quoted" name"#, 2, micros(&[1, 3]));
+        assert_eq!(
+            report.to_json(),
+            concat!(
+                r#"{"name":"a \"quoted\" name","warmup":2,"samples":2,"min_ns":1000,"#,
+                r#""max_ns":3000,"mean_ns":2000,"std_dev_ns":1000,"median_ns":1000,"#,
+                r#""p90_ns":3000,"p99_ns":3000,"low_outliers":0,"high_outliers":0}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_json_string_escapes_control_characters() {
+        assert_eq!(json_string("plain"), r#""plain""#);
+        assert_eq!(
+            json_string("a \"b\" \\ c\nd\r\te\u{1}"),
+            r#""a \"b\" \\ c\nd\r\te\u0001""#
+        );
+        let report = BenchReport::from_samples("two\nlines", 0, micros(&[1]));
+        assert!(report.to_json().starts_with(r#"{"name":"two\nlines","#));
+    }
+
+    #[test]
+    fn test_run_with_state_warms_up_then_samples() {
+        let config = BenchConfig::default().warmup(3).samples(7);
+        let mut calls = 0;
+        let report = run_with_state("count", &config, &mut calls, |calls| **calls += 1);
+        assert_eq!(calls, 10);
+        assert_eq!(report.samples, 7);
+        assert_eq!(report.warmup, 3);
+    }
+}
@@ -0,0 +1,171 @@
+// A `Category` trait so that code can be written once over any category,
+// w/ instances for plain Rust functions (ch1) and for the Kleisli category of
+// any monad, e.g. ch4's (partial functions via `Option`, logging via `Writer`).
+//
+// Morphisms are boxed trait objects: `compose` has to return a nameable type
+// to fit `Hom<A, B>`, which rules out ch1's `impl Fn`.
+
+#![allow(unused)]
+
+use std::marker::PhantomData;
+
+use crate::ch1;
+use crate::ch4::writer::Writer;
+use crate::monad::{kleisli_compose, Monad, OptionM, WriterM};
+
+pub trait Category {
+    // morphisms A -> B
+    type Hom<A: 'static, B: 'static>;
+    // what running a morphism A -> B actually yields (B, Option<B>, ...)
+    type Out<B>;
+
+    fn id<A: 'static>() -> Self::Hom<A, A>;
+
+    // f then g (i.e. g . f), same argument order as ch1::compose
+    fn compose<A: 'static, B: 'static, C: 'static>(
+        f: Self::Hom<A, B>,
+        g: Self::Hom<B, C>,
+    ) -> Self::Hom<A, C>;
+
+    fn apply<A: 'static, B: 'static>(f: &Self::Hom<A, B>, a: A) -> Self::Out<B>;
+}
+
+// Rust types and (pure) functions
+pub struct FnCat;
+
+impl Category for FnCat {
+    type Hom<A: 'static, B: 'static> = Box<dyn Fn(A) -> B>;
+    type Out<B> = B;
+
+    fn id<A: 'static>() -> Self::Hom<A, A> {
+        Box::new(ch1::id)
+    }
+
+    fn compose<A: 'static, B: 'static, C: 'static>(
+        f: Self::Hom<A, B>,
+        g: Self::Hom<B, C>,
+    ) -> Self::Hom<A, C> {
+        Box::new(ch1::compose(f, g))
+    }
+
+    fn apply<A: 'static, B: 'static>(f: &Self::Hom<A, B>, a: A) -> B {
+        f(a)
+    }
+}
+
+// the Kleisli category of any monad K: A -> K::M<B>
+pub struct KleisliCat<K>(PhantomData<K>);
+
+impl<K: Monad> Category for KleisliCat<K> {
+    type Hom<A: 'static, B: 'static> = Box<dyn Fn(A) -> K::M<B>>;
+    type Out<B> = K::M<B>;
+
+    fn id<A: 'static>() -> Self::Hom<A, A> {
+        Box::new(K::pure)
+    }
+
+    fn compose<A: 'static, B: 'static, C: 'static>(
+        f: Self::Hom<A, B>,
+        g: Self::Hom<B, C>,
+    ) -> Self::Hom<A, C> {
+        Box::new(kleisli_compose::<K, _, _, _>(f, g))
+    }
+
+    fn apply<A: 'static, B: 'static>(f: &Self::Hom<A, B>, a: A) -> K::M<B> {
+        f(a)
+    }
+}
+
+// Kleisli category for partial functions: A -> Option<B>
+pub type OptionKleisli = KleisliCat<OptionM>;
+
+// Kleisli category for the (String-logging) Writer: A -> Writer<B>
+pub type WriterKleisli = KleisliCat<WriterM>;
+
+// compose a chain of endomorphisms left to right; the empty chain is `id`
+pub fn compose_all<C: Category, A: 'static>(
+    fs: impl IntoIterator<Item = C::Hom<A, A>>,
+) -> C::Hom<A, A> {
+    fs.into_iter().fold(C::id(), C::compose)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ch4::optional::{self, safe_reciprocal, safe_root};
+    use crate::ch4::writer::{to_words, upcase};
+
+    fn double(x: u32) -> u32 {
+        x * 2
+    }
+
+    fn logged_double(x: u32) -> Writer<u32> {
+        Writer(x * 2, "double ".to_string())
+    }
+
+    #[test]
+    fn test_fn_cat_id_laws() {
+        let double_dot_id = FnCat::compose(FnCat::id(), Box::new(double));
+        let id_dot_double = FnCat::compose(Box::new(double), FnCat::id());
+        assert_eq!(FnCat::apply(&double_dot_id, 42), double(42));
+        assert_eq!(FnCat::apply(&id_dot_double, 42), double(42));
+    }
+
+    #[test]
+    fn test_option_kleisli_matches_safe_root_reciprocal() {
+        let f = OptionKleisli::compose(Box::new(safe_reciprocal), Box::new(safe_root));
+        for x in [-4.0, 0.0, 4.0] {
+            assert_eq!(
+                OptionKleisli::apply(&f, x),
+                optional::safe_root_reciprocal(x)
+            );
+        }
+    }
+
+    #[test]
+    fn test_option_kleisli_changes_type() {
+        let parse: Box<dyn Fn(&'static str) -> Option<u32>> = Box::new(|s| s.parse().ok());
+        let halve: Box<dyn Fn(u32) -> Option<f64>> =
+            Box::new(|n| (n % 2 == 0).then_some(n as f64 / 2.0));
+        let f = OptionKleisli::compose(parse, halve);
+        assert_eq!(f("42"), Some(21.0));
+        assert_eq!(f("41"), None);
+        assert_eq!(f("forty-two"), None);
+    }
+
+    #[test]
+    fn test_writer_kleisli_id_laws() {
+        let upcase_to_words = WriterKleisli::compose(Box::new(upcase), Box::new(to_words));
+        let with_ids = WriterKleisli::compose(
+            WriterKleisli::id(),
+            WriterKleisli::compose(upcase_to_words, WriterKleisli::id()),
+        );
+        let s = "so long".to_string();
+        assert_eq!(
+            WriterKleisli::apply(&with_ids, s),
+            Writer(
+                vec!["SO".to_string(), "LONG".to_string()],
+                "upcase to_words ".to_string()
+            )
+        );
+    }
+
+    // the same generic code, run in three different categories
+    #[test]
+    fn test_compose_all_is_generic_over_categories() {
+        let fs: Vec<Box<dyn Fn(u32) -> u32>> = vec![Box::new(double), Box::new(double)];
+        assert_eq!(compose_all::<FnCat, _>(fs)(3), 12);
+        assert_eq!(compose_all::<FnCat, u32>([])(3), 3);
+
+        let fs: Vec<Box<dyn Fn(f64) -> Option<f64>>> =
+            vec![Box::new(safe_root), Box::new(safe_reciprocal)];
+        assert_eq!(compose_all::<OptionKleisli, _>(fs)(16.0), Some(0.25));
+
+        let fs: Vec<Box<dyn Fn(u32) -> Writer<u32>>> =
+            vec![Box::new(logged_double), Box::new(logged_double)];
+        assert_eq!(
+            compose_all::<WriterKleisli, _>(fs)(3),
+            Writer(12, "double double ".to_string())
+        );
+    }
+}
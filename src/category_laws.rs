@@ -0,0 +1,280 @@
+// Law checking for categories: left/right identity and associativity,
+// checked pointwise over sample inputs for any `Category` instance.
+//
+// Morphisms are passed as factories (`Fn() -> Hom<A, B>`) as boxed morphisms
+// can't be cloned, and each law needs to use the same morphism twice.
+
+#![allow(unused)]
+
+use std::error::Error;
+use std::fmt::{self, Debug};
+
+use crate::categories::Category;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Law {
+    LeftIdentity,
+    RightIdentity,
+    Associativity,
+    // of monoid homomorphisms
+    PreservesEmpty,
+    PreservesCombine,
+}
+
+impl fmt::Display for Law {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Law::LeftIdentity => "left identity (id >>> f = f)",
+            Law::RightIdentity => "right identity (f >>> id = f)",
+            Law::Associativity => "associativity ((f >>> g) >>> h = f >>> (g >>> h))",
+            Law::PreservesEmpty => "preservation of empty (h(empty) = empty)",
+            Law::PreservesCombine => "preservation of combine (h(a <> b) = h(a) <> h(b))",
+        })
+    }
+}
+
+// the counterexample: which law, on which input, and both sides' results
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LawViolation {
+    pub law: Law,
+    pub input: String,
+    pub lhs: String,
+    pub rhs: String,
+}
+
+impl fmt::Display for LawViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} violated for input {}: {} != {}",
+            self.law, self.input, self.lhs, self.rhs
+        )
+    }
+}
+
+impl Error for LawViolation {}
+
+pub(crate) fn check_pointwise<A: Clone + Debug, T: PartialEq + Debug>(
+    law: Law,
+    lhs: impl Fn(A) -> T,
+    rhs: impl Fn(A) -> T,
+    samples: impl IntoIterator<Item = A>,
+) -> Result<(), LawViolation> {
+    for a in samples {
+        let (l, r) = (lhs(a.clone()), rhs(a.clone()));
+        if l != r {
+            return Err(LawViolation {
+                law,
+                input: format!("{:?}", a),
+                lhs: format!("{:?}", l),
+                rhs: format!("{:?}", r),
+            });
+        }
+    }
+    Ok(())
+}
+
+pub fn check_left_identity<C, A, B>(
+    f: impl Fn() -> C::Hom<A, B>,
+    samples: impl IntoIterator<Item = A>,
+) -> Result<(), LawViolation>
+where
+    C: Category,
+    A: Clone + Debug + 'static,
+    B: 'static,
+    C::Out<B>: PartialEq + Debug,
+{
+    let id_then_f = C::compose(C::id(), f());
+    let f = f();
+    check_pointwise(
+        Law::LeftIdentity,
+        |a| C::apply(&id_then_f, a),
+        |a| C::apply(&f, a),
+        samples,
+    )
+}
+
+pub fn check_right_identity<C, A, B>(
+    f: impl Fn() -> C::Hom<A, B>,
+    samples: impl IntoIterator<Item = A>,
+) -> Result<(), LawViolation>
+where
+    C: Category,
+    A: Clone + Debug + 'static,
+    B: 'static,
+    C::Out<B>: PartialEq + Debug,
+{
+    let f_then_id = C::compose(f(), C::id());
+    let f = f();
+    check_pointwise(
+        Law::RightIdentity,
+        |a| C::apply(&f_then_id, a),
+        |a| C::apply(&f, a),
+        samples,
+    )
+}
+
+pub fn check_associativity<C, A, B, X, D>(
+    f: impl Fn() -> C::Hom<A, B>,
+    g: impl Fn() -> C::Hom<B, X>,
+    h: impl Fn() -> C::Hom<X, D>,
+    samples: impl IntoIterator<Item = A>,
+) -> Result<(), LawViolation>
+where
+    C: Category,
+    A: Clone + Debug + 'static,
+    B: 'static,
+    X: 'static,
+    D: 'static,
+    C::Out<D>: PartialEq + Debug,
+{
+    let left_assoc = C::compose(C::compose(f(), g()), h());
+    let right_assoc = C::compose(f(), C::compose(g(), h()));
+    check_pointwise(
+        Law::Associativity,
+        |a| C::apply(&left_assoc, a),
+        |a| C::apply(&right_assoc, a),
+        samples,
+    )
+}
+
+// all three laws; the identity laws are checked for f only, as g and h are
+// only reachable through f's output
+pub fn check_category_laws<C, A, B, X, D>(
+    f: impl Fn() -> C::Hom<A, B>,
+    g: impl Fn() -> C::Hom<B, X>,
+    h: impl Fn() -> C::Hom<X, D>,
+    samples: impl IntoIterator<Item = A>,
+) -> Result<(), LawViolation>
+where
+    C: Category,
+    A: Clone + Debug + 'static,
+    B: 'static,
+    X: 'static,
+    D: 'static,
+    C::Out<B>: PartialEq + Debug,
+    C::Out<D>: PartialEq + Debug,
+{
+    let samples: Vec<A> = samples.into_iter().collect();
+    check_left_identity::<C, _, _>(&f, samples.iter().cloned())?;
+    check_right_identity::<C, _, _>(&f, samples.iter().cloned())?;
+    check_associativity::<C, _, _, _, _>(f, g, h, samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::categories::{FnCat, OptionKleisli, WriterKleisli};
+    use crate::ch4::optional::{safe_reciprocal, safe_root};
+    use crate::ch4::writer::{self, to_words, upcase, Writer};
+
+    #[test]
+    fn test_fn_cat_laws() {
+        let result = check_category_laws::<FnCat, _, _, _, _>(
+            || Box::new(|x: u32| x + 1),
+            || Box::new(|x: u32| x * 2),
+            || Box::new(|x: u32| x.to_string()),
+            0..100,
+        );
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_option_kleisli_laws() {
+        let samples = [-4.0, -1.0, 0.0, 0.25, 1.0, 4.0, 16.0];
+        let result = check_category_laws::<OptionKleisli, _, _, _, _>(
+            || Box::new(safe_root),
+            || Box::new(safe_reciprocal),
+            || Box::new(|x: f64| (x < 1.0).then_some(x.to_string())),
+            samples,
+        );
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_writer_kleisli_laws() {
+        let samples = ["", "a b", "And now for something completely different"];
+        let result = check_category_laws::<WriterKleisli, _, _, _, _>(
+            || Box::new(|s: &str| Writer(s.to_string(), "own ".to_string())),
+            || Box::new(upcase),
+            || Box::new(to_words),
+            samples,
+        );
+        assert_eq!(result, Ok(()));
+    }
+
+    // a Writer-like category whose composition appends a spurious marker to
+    // the log, so that composing with id is no longer a no-op
+    struct LeakyWriterKleisli;
+
+    impl Category for LeakyWriterKleisli {
+        type Hom<A: 'static, B: 'static> = Box<dyn Fn(A) -> Writer<B>>;
+        type Out<B> = Writer<B>;
+
+        fn id<A: 'static>() -> Self::Hom<A, A> {
+            Box::new(writer::identity_morphism)
+        }
+
+        fn compose<A: 'static, B: 'static, C: 'static>(
+            f: Self::Hom<A, B>,
+            g: Self::Hom<B, C>,
+        ) -> Self::Hom<A, C> {
+            Box::new(move |a| {
+                let Writer(b, s1) = f(a);
+                let Writer(c, s2) = g(b);
+                Writer(c, s1 + &s2 + "!")
+            })
+        }
+
+        fn apply<A: 'static, B: 'static>(f: &Self::Hom<A, B>, a: A) -> Writer<B> {
+            f(a)
+        }
+    }
+
+    #[test]
+    fn test_violation_reports_counterexample() {
+        let violation = check_left_identity::<LeakyWriterKleisli, _, _>(
+            || Box::new(upcase),
+            ["hi".to_string()],
+        )
+        .unwrap_err();
+        assert_eq!(violation.law, Law::LeftIdentity);
+        assert_eq!(violation.input, r#""hi""#);
+        assert_eq!(violation.lhs, r#"Writer(['H', 'I'], "upcase !")"#);
+        assert_eq!(violation.rhs, r#"Writer(['H', 'I'], "upcase ")"#);
+        assert!(violation.to_string().starts_with("left identity"));
+
+        // the marker also ends up in different places depending on bracketing
+        let violation = check_associativity::<LeakyWriterKleisli, _, _, _, _>(
+            || Box::new(upcase),
+            || Box::new(to_words),
+            || Box::new(|ws: Vec<String>| Writer(ws.len(), "len ".to_string())),
+            ["a b".to_string()],
+        )
+        .unwrap_err();
+        assert_eq!(violation.law, Law::Associativity);
+        assert_eq!(violation.lhs, r#"Writer(2, "upcase to_words !len !")"#);
+        assert_eq!(violation.rhs, r#"Writer(2, "upcase to_words len !!")"#);
+    }
+
+    #[test]
+    fn test_violation_reports_first_failing_input() {
+        // not a function in the mathematical sense: depends on a counter
+        let calls = std::cell::Cell::new(0u32);
+        let violation = check_pointwise(
+            Law::RightIdentity,
+            |x: u32| x,
+            |x: u32| {
+                calls.set(calls.get() + 1);
+                if calls.get() > 3 {
+                    x + 1
+                } else {
+                    x
+                }
+            },
+            10..20,
+        )
+        .unwrap_err();
+        assert_eq!(violation.input, "13");
+    }
+}
@@ -19,6 +19,30 @@ where
     move |arg| g(f(arg))
 }
 
+// variadic composition, left to right: `compose!(f, g, h)` is `h . g . f`.
+// expands to nested `compose` calls, so the id laws carry over unchanged;
+// `compose!()` is `id`
+#[macro_export]
+macro_rules! compose {
+    () => {
+        $crate::category::id
+    };
+    ($f:expr $(,)?) => {
+        $f
+    };
+    ($f:expr, $g:expr $(, $rest:expr)* $(,)?) => {
+        $crate::compose!($crate::category::compose($f, $g) $(, $rest)*)
+    };
+}
+
+// feed a value through a left-to-right chain: `pipe!(x => f, g, h)` is `h(g(f(x)))`
+#[macro_export]
+macro_rules! pipe {
+    ($x:expr => $($f:expr),* $(,)?) => {
+        ($crate::compose!($($f),*))($x)
+    };
+}
+
 // extracting out compositions would make for greater clarity
 #[cfg(test)]
 mod tests {
@@ -46,6 +70,17 @@ mod tests {
         assert_eq!(id(x), (compose(id, id))(x));
     }
 
+    // ... and at every point, over a few finite types
+    #[test]
+    fn test_compose_id_with_itself_everywhere() {
+        use crate::finite_types::ext_eq;
+        use std::cmp::Ordering;
+
+        assert!(ext_eq(compose(id::<bool>, id), id));
+        assert!(ext_eq(compose(id::<Ordering>, id), id));
+        assert!(ext_eq(compose(id::<(bool, Option<()>)>, id), id));
+    }
+
     #[test]
     fn test_id_composed_with_double_is_double() {
         let x = double(id(42));
@@ -59,4 +94,42 @@ mod tests {
         let id_dot_double = compose(id, double);
         assert_eq!(x, id_dot_double(42));
     }
+
+    #[test]
+    fn compose_macro_matches_nested_compose() {
+        let nested = compose(compose(compose(add_one, double), add_one), double);
+        let chained = crate::compose!(add_one, double, add_one, double);
+        assert_eq!(nested(3), chained(3));
+        assert_eq!(chained(3), 18);
+    }
+
+    // id . id . ... . id = id, for chains of increasing length
+    #[test]
+    fn test_compose_macro_id_chains() {
+        let x = "id";
+        assert_eq!(id(x), (crate::compose!())(x));
+        assert_eq!(id(x), (crate::compose!(id))(x));
+        assert_eq!(id(x), (crate::compose!(id, id))(x));
+        assert_eq!(id(x), (crate::compose!(id, id, id))(x));
+        assert_eq!(id(x), (crate::compose!(id, id, id, id, id, id, id, id))(x));
+    }
+
+    #[test]
+    fn test_compose_macro_id_anywhere_in_chain() {
+        let expected = double(add_one(double(42)));
+        assert_eq!(expected, crate::compose!(id, double, add_one, double)(42));
+        assert_eq!(
+            expected,
+            crate::compose!(double, id, add_one, id, double)(42)
+        );
+        assert_eq!(expected, crate::compose!(double, add_one, double, id)(42));
+    }
+
+    #[test]
+    fn test_pipe_macro() {
+        assert_eq!(crate::pipe!(1 => add_one, double, add_one), 5);
+        assert_eq!(crate::pipe!(1 => add_one, double,), 4);
+        assert_eq!(crate::pipe!("id" => id, id, id), id("id"));
+        assert_eq!(crate::pipe!(42 =>), 42);
+    }
 }
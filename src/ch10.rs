@@ -3,7 +3,7 @@
 #![allow(unused)]
 
 // safeHead Natural transformation: List -> Maybe functor (&[T] -> Option<T>)
-fn safe_head<T: Clone>(xs: &[T]) -> Option<T> {
+pub fn safe_head<T: Clone>(xs: &[T]) -> Option<T> {
     xs.first().cloned()
 }
 
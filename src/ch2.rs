@@ -1,33 +1,281 @@
 // <https://bartoszmilewski.com/2014/11/24/types-and-functions/>
 // 1. implement memoize
+//    (+ open-recursive variant that also caches intermediate calls)
 // 5. implement all Bool -> Bool funcs
 
 #![allow(dead_code)]
+use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use crate::currying_alt::Tupled;
+use crate::memoize_eviction::{EvictionPolicy, Unbounded};
 
 // 1. memoize
 // using a wrapper struct allows us to inspect the cache
-struct Memoizer<T, U> {
-    f: Box<dyn Fn(T) -> U>,
+pub struct Memoizer<T, U> {
+    f: Box<RecFn<T, U>>,
+    cache: Cache<T, U>,
+}
+
+// open recursion: the function gets a handle back into the memoizer, which
+// it uses in place of calling itself directly
+type RecFn<T, U> = dyn Fn(&mut dyn FnMut(T) -> U, T) -> U;
+
+type AdmitFn<U> = dyn Fn(&U) -> bool;
+
+// everything but the function, so that the function can be borrowed
+// alongside the (mutably borrowed) cache while recursing
+struct Cache<T, U> {
     store: HashMap<T, U>,
+    policy: Box<dyn EvictionPolicy<T>>,
+    stats: CacheStats,
+    // results failing this aren't cached
+    admit: Option<Box<AdmitFn<U>>>,
+    // nesting level of recursive calls into `f`, so that only the outermost
+    // one is timed
+    depth: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub inserts: u64,
+    pub evictions: u64,
+    // entries currently cached
+    pub size: usize,
+    // cumulative wall time spent inside the wrapped function
+    pub compute_time: Duration,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash, U: Clone> Cache<T, U> {
+    // policies only know about owned keys, hence going through the stored key
+    fn get<Q: Hash + Eq + ?Sized>(&mut self, arg: &Q) -> Option<U>
+    where
+        T: Borrow<Q>,
+    {
+        let (k, v) = self.store.get_key_value(arg)?;
+        if self.policy.is_expired(k) {
+            self.policy.on_remove(k);
+            self.store.remove(arg);
+            self.stats.evictions += 1;
+            return None;
+        }
+        self.policy.on_hit(k);
+        Some(v.clone())
+    }
+
+    fn insert(&mut self, arg: T, v: U) {
+        for victim in self.policy.on_insert(&arg) {
+            self.store.remove(&victim);
+            self.stats.evictions += 1;
+        }
+        self.store.insert(arg, v);
+        self.stats.inserts += 1;
+    }
+}
+
+// one level deeper into `f` while alive; dropping it (even while unwinding
+// from a panic in `f`) steps back out
+struct Nested<'a, T, U>(&'a mut Cache<T, U>);
+
+impl<'a, T, U> Nested<'a, T, U> {
+    fn enter(cache: &'a mut Cache<T, U>) -> Self {
+        cache.depth += 1;
+        Nested(cache)
+    }
+}
+
+impl<T, U> Drop for Nested<'_, T, U> {
+    fn drop(&mut self) {
+        self.0.depth -= 1;
+    }
 }
 
 impl<T: Clone + Eq + Hash, U: Clone> Memoizer<T, U> {
-    fn new(f: impl Fn(T) -> U + 'static) -> Self {
+    pub fn new(f: impl Fn(T) -> U + 'static) -> Self {
+        Self::recursive(move |_, x| f(x))
+    }
+
+    // functions of 2 to 6 arguments, memoized on the tuple of their arguments:
+    // `Memoizer::new_multi(|s: String, c: char| ...).call(("element".into(), 'e'))`;
+    // see `new_multi_borrowed` for functions taking borrowed arguments
+    pub fn new_multi(f: impl Tupled<T, U> + 'static) -> Self {
+        Self::new(move |args| f.call_tupled(args))
+    }
+
+    // e.g. `Memoizer::recursive(|fib, n| if n < 2 { n } else { fib(n - 1) + fib(n - 2) })`
+    // caches every intermediate call, turning exponential fib into linear
+    pub fn recursive(f: impl Fn(&mut dyn FnMut(T) -> U, T) -> U + 'static) -> Self {
         Self {
             f: Box::new(f),
-            store: HashMap::new(),
+            cache: Cache {
+                store: HashMap::new(),
+                policy: Box::new(Unbounded),
+                stats: CacheStats::default(),
+                admit: None,
+                depth: 0,
+            },
+        }
+    }
+
+    // bound the cache, e.g. `Memoizer::new(f).with_policy(Lru::new(128))`;
+    // anything already cached is handed to the new policy
+    pub fn with_policy(mut self, policy: impl EvictionPolicy<T> + 'static) -> Self {
+        let cached = std::mem::take(&mut self.cache.store);
+        self.cache.policy = Box::new(policy);
+        for (k, v) in cached {
+            self.cache.insert(k, v);
+        }
+        self
+    }
+
+    // only cache results satisfying `pred`, e.g. `.cache_if(Result::is_ok)`;
+    // the others are recomputed on every call
+    pub fn cache_if(mut self, pred: impl Fn(&U) -> bool + 'static) -> Self {
+        self.cache.admit = Some(Box::new(pred));
+        self
+    }
+
+    // number of entries dropped by the eviction policy so far
+    pub fn evictions(&self) -> u64 {
+        self.cache.stats.evictions
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            size: self.cache.store.len(),
+            ..self.cache.stats
+        }
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.cache.stats = CacheStats::default();
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.store.is_empty()
+    }
+
+    // drop `arg`'s cached value (not counted as an eviction)
+    pub fn invalidate<Q: Hash + Eq + ?Sized>(&mut self, arg: &Q) -> Option<U>
+    where
+        T: Borrow<Q>,
+    {
+        let (k, v) = self.cache.store.remove_entry(arg)?;
+        self.cache.policy.on_remove(&k);
+        Some(v)
+    }
+
+    // cached value, if any, w/o computing it (nor counting as a hit/miss)
+    pub fn peek<Q: Hash + Eq + ?Sized>(&self, arg: &Q) -> Option<&U>
+    where
+        T: Borrow<Q>,
+    {
+        let (k, v) = self.cache.store.get_key_value(arg)?;
+        (!self.cache.policy.is_expired(k)).then_some(v)
+    }
+
+    pub fn clear(&mut self) {
+        self.cache.policy.clear();
+        self.cache.store.clear();
+    }
+
+    // seed the cache w/ an already known result (e.g. from a snapshot)
+    pub(crate) fn insert(&mut self, arg: T, v: U) {
+        self.cache.insert(arg, v);
+    }
+
+    // cached entries, in no particular order; expired entries that haven't
+    // been evicted yet are skipped
+    pub fn iter(&self) -> impl Iterator<Item = (&T, &U)> {
+        let policy = &self.cache.policy;
+        self.cache
+            .store
+            .iter()
+            .filter(move |(k, _)| !policy.is_expired(k))
+    }
+
+    // for functions built w/ `new` we just wrap the initial call to `f`;
+    // recursive functions' intermediate results are only cached when
+    // recursing through the handle passed in by `recursive`
+    pub fn call(&mut self, arg: T) -> U {
+        Self::lookup_or_compute(&*self.f, &mut self.cache, arg)
+    }
+
+    // look up by borrowed form, e.g. `&str` for `String` keys: the key is
+    // only converted to its owned form (allocated) on a miss. Tuple keys
+    // don't borrow as tuples of borrowed forms; see `call2` to `call6` for those
+    pub fn call_borrowed<Q>(&mut self, arg: &Q) -> U
+    where
+        T: Borrow<Q>,
+        Q: ToOwned<Owned = T> + Hash + Eq + ?Sized,
+    {
+        self.call_by(arg, || arg.to_owned())
+    }
+
+    // as `call_borrowed`, w/ the owned key made by `to_owned` on a miss
+    pub(crate) fn call_by<Q>(&mut self, arg: &Q, to_owned: impl FnOnce() -> T) -> U
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.cache.get(arg) {
+            Some(v) => {
+                self.cache.stats.hits += 1;
+                v
+            }
+            None => self.compute(to_owned()),
         }
     }
 
-    // we just wrap the initial call to `f`;
-    // recursive functions' intermediate results are not cached
-    fn call(&mut self, arg: T) -> U {
-        self.store
-            .entry(arg)
-            .or_insert_with_key(|x| (self.f)(x.clone()))
-            .clone()
+    fn lookup_or_compute(f: &RecFn<T, U>, cache: &mut Cache<T, U>, arg: T) -> U {
+        if let Some(v) = cache.get(&arg) {
+            cache.stats.hits += 1;
+            return v;
+        }
+        Self::compute_uncached(f, cache, arg)
+    }
+
+    fn compute(&mut self, arg: T) -> U {
+        Self::compute_uncached(&*self.f, &mut self.cache, arg)
+    }
+
+    fn compute_uncached(f: &RecFn<T, U>, cache: &mut Cache<T, U>, arg: T) -> U {
+        cache.stats.misses += 1;
+
+        let start = (cache.depth == 0).then(Instant::now);
+        let v = {
+            let nested = Nested::enter(cache);
+            // can't hold on to an `entry` here: recursing needs the cache back
+            f(
+                &mut |x| Self::lookup_or_compute(f, nested.0, x),
+                arg.clone(),
+            )
+        };
+        if let Some(start) = start {
+            cache.stats.compute_time += start.elapsed();
+        }
+
+        if cache.admit.as_ref().is_none_or(|admit| admit(&v)) {
+            cache.insert(arg, v.clone());
+        }
+        v
     }
 }
 
@@ -52,7 +300,11 @@ fn diverge_bool(_: bool) -> ! {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::{Duration, Instant};
+    use crate::benchmark::BenchConfig;
+    use crate::currying_alt::count_char;
+    use crate::finite_types::{count_functions, FnTable};
+    use crate::memoize_eviction::{Lfu, Lru, ManualClock, Ttl};
+    use std::hint::black_box;
 
     fn gen_fib_nums_to_ten() -> HashMap<u32, u32> {
         HashMap::from([
@@ -77,6 +329,13 @@ mod tests {
         }
     }
 
+    fn fib_open(fib: &mut dyn FnMut(u32) -> u32, n: u32) -> u32 {
+        match n {
+            0 | 1 => n,
+            _ => fib(n - 1) + fib(n - 2),
+        }
+    }
+
     #[test]
     fn test_fibonacci_correctness() {
         let mut fib_memoized = Memoizer::new(fib);
@@ -91,64 +350,329 @@ mod tests {
             fib_memoized.call(i);
         }
 
-        assert_eq!(fib_memoized.store, gen_fib_nums_to_ten());
+        assert_eq!(fib_memoized.cache.store, gen_fib_nums_to_ten());
     }
 
-    // naive pseudo-benchmarks for ballpark figures in lieu of using either
-    // Unstable's test::Benchmark or Criterion.
-    // [should really be macro_rules!()'ed to reduce duplication]
-    // run w/ `cargo test -- --include-ignored --show-output`
-    #[ignore]
     #[test]
-    fn bench_fib() {
-        let mut d: Duration = Default::default();
+    fn test_recursive_fibonacci_caches_intermediate_calls() {
+        let mut fib_memoized = Memoizer::recursive(fib_open);
 
-        for _ in 0..1000 {
-            let start = Instant::now();
-            for i in 0..=30 {
-                fib(i);
+        assert_eq!(55, fib_memoized.call(10));
+        assert_eq!(fib_memoized.cache.store, gen_fib_nums_to_ten());
+    }
+
+    #[test]
+    fn test_recursive_fibonacci_is_linear() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        let mut fib_memoized = Memoizer::recursive(move |fib, n: u64| {
+            counter.set(counter.get() + 1);
+            match n {
+                0 | 1 => n,
+                _ => fib(n - 1) + fib(n - 2),
             }
-            d += start.elapsed();
-        }
+        });
 
-        println!("{:?}", d / 1000);
+        assert_eq!(fib_memoized.call(90), 2_880_067_194_370_816_120);
+        assert_eq!(calls.get(), 91);
+
+        // everything up to 90 is cached now
+        fib_memoized.call(80);
+        assert_eq!(calls.get(), 91);
     }
 
-    #[ignore]
     #[test]
-    fn bench_fib_memoized_no_warmup() {
+    fn test_stats() {
         let mut fib_memoized = Memoizer::new(fib);
-        let mut d: Duration = Default::default();
 
-        for _ in 0..1000 {
-            let start = Instant::now();
-            for i in 0..=30 {
-                fib_memoized.call(i);
+        for i in 0..=10 {
+            fib_memoized.call(i);
+        }
+        for i in 5..=15 {
+            fib_memoized.call(i);
+        }
+
+        let stats = fib_memoized.stats();
+        assert_eq!(stats.hits, 6);
+        assert_eq!(stats.misses, 16);
+        assert_eq!(stats.inserts, 16);
+        assert_eq!(stats.evictions, 0);
+        assert_eq!(stats.size, 16);
+        assert_eq!(stats.hit_rate(), 6.0 / 22.0);
+
+        fib_memoized.reset_stats();
+        assert_eq!(fib_memoized.stats().hits, 0);
+        assert_eq!(fib_memoized.stats().size, 16);
+    }
+
+    #[test]
+    fn test_stats_compute_time_is_not_double_counted() {
+        let mut slow = Memoizer::recursive(|recur, n: u32| {
+            std::thread::sleep(Duration::from_millis(5));
+            if n == 0 {
+                0
+            } else {
+                recur(n - 1)
+            }
+        });
+
+        let start = Instant::now();
+        slow.call(3);
+        let elapsed = start.elapsed();
+
+        let stats = slow.stats();
+        assert_eq!(stats.misses, 4);
+        assert!(stats.compute_time >= Duration::from_millis(20));
+        assert!(stats.compute_time <= elapsed);
+    }
+
+    #[test]
+    fn test_stats_compute_time_after_a_panic() {
+        let mut slow = Memoizer::recursive(|recur, n: u32| {
+            assert_ne!(n, 0, "boom");
+            std::thread::sleep(Duration::from_millis(5));
+            if n == 1 {
+                1
+            } else {
+                recur(n - 1)
             }
-            d += start.elapsed();
+        });
+        let caught = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| slow.call(0)));
+        assert!(caught.is_err());
+
+        slow.call(2);
+        assert!(slow.stats().compute_time >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_invalidate_clear_and_iter() {
+        let mut fib_memoized = Memoizer::recursive(fib_open);
+        fib_memoized.call(10);
+
+        let mut cached: Vec<(u32, u32)> = fib_memoized.iter().map(|(&k, &v)| (k, v)).collect();
+        cached.sort();
+        let mut expected: Vec<(u32, u32)> = gen_fib_nums_to_ten().into_iter().collect();
+        expected.sort();
+        assert_eq!(cached, expected);
+
+        assert_eq!(fib_memoized.invalidate(&10), Some(55));
+        assert_eq!(fib_memoized.invalidate(&10), None);
+        assert_eq!(fib_memoized.len(), 10);
+        assert_eq!(fib_memoized.evictions(), 0);
+
+        // recomputing 10 only needs the (still cached) 9 and 8
+        fib_memoized.reset_stats();
+        fib_memoized.call(10);
+        assert_eq!(fib_memoized.stats().misses, 1);
+        assert_eq!(fib_memoized.stats().hits, 2);
+
+        fib_memoized.clear();
+        assert!(fib_memoized.is_empty());
+        assert_eq!(fib_memoized.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_multi_arg_memoizer() {
+        // owned keys, looked up by the arguments `count_char` takes
+        let mut count_memoized: Memoizer<(String, char), usize> =
+            Memoizer::new_multi_borrowed(count_char);
+        assert_eq!(count_memoized.call2("element", 'e'), 3);
+        assert_eq!(count_memoized.call2("element", 'e'), 3);
+        assert_eq!(count_memoized.call2("element", 'l'), 1);
+        assert_eq!(count_memoized.stats().hits, 1);
+        assert_eq!(count_memoized.peek(&("element".to_string(), 'l')), Some(&1));
+
+        let mut volume = Memoizer::new_multi(|a: u32, b: u32, c: u32, d: u32, e: u32, f: u32| {
+            a * b * c * d * e * f
+        });
+        assert_eq!(volume.call((1, 2, 3, 4, 5, 6)), 720);
+        assert_eq!(volume.len(), 1);
+    }
+
+    #[test]
+    fn test_borrowed_key_lookups() {
+        let mut len_memoized = Memoizer::new(|s: String| s.len());
+
+        assert_eq!(len_memoized.call_borrowed("hello"), 5);
+        assert_eq!(len_memoized.call_borrowed("hello"), 5);
+        assert_eq!(len_memoized.call("hello".to_string()), 5);
+
+        let stats = len_memoized.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 1));
+
+        assert_eq!(len_memoized.peek("hello"), Some(&5));
+        assert_eq!(len_memoized.peek("world"), None);
+        assert_eq!(len_memoized.invalidate("hello"), Some(5));
+        assert!(len_memoized.is_empty());
+    }
+
+    #[test]
+    fn test_borrowed_key_lookups_respect_policy() {
+        let mut len_memoized = Memoizer::new(|s: String| s.len()).with_policy(Lru::new(2));
+
+        len_memoized.call_borrowed("a");
+        len_memoized.call_borrowed("bb");
+        // a hit through the borrowed form still counts as a use for the LRU
+        len_memoized.call_borrowed("a");
+        len_memoized.call_borrowed("ccc");
+
+        assert_eq!(len_memoized.peek("a"), Some(&1));
+        assert_eq!(len_memoized.peek("bb"), None);
+        assert_eq!(len_memoized.evictions(), 1);
+    }
+
+    #[test]
+    fn test_cache_if() {
+        let mut parsed = Memoizer::new(|s: String| s.parse::<u32>()).cache_if(Result::is_ok);
+
+        assert_eq!(parsed.call("42".to_string()), Ok(42));
+        assert!(parsed.call("x".to_string()).is_err());
+        assert!(parsed.call("x".to_string()).is_err());
+
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed.stats().misses, 3);
+        assert_eq!(parsed.stats().inserts, 1);
+    }
+
+    #[test]
+    fn test_lru_bounded_memoizer() {
+        let mut fib_memoized = Memoizer::new(fib).with_policy(Lru::new(3));
+
+        for i in 0..=10 {
+            fib_memoized.call(i);
         }
+        fib_memoized.call(8);
+
+        assert_eq!(fib_memoized.cache.store.len(), 3);
+        assert_eq!(fib_memoized.evictions(), 8);
+        assert_eq!(
+            fib_memoized.cache.store,
+            HashMap::from([(8, 21), (9, 34), (10, 55)])
+        );
 
-        println!("{:?}", d / 1000);
+        // 9 is the LRU entry, so it makes room for 0
+        fib_memoized.call(0);
+        assert_eq!(
+            fib_memoized.cache.store,
+            HashMap::from([(0, 0), (8, 21), (10, 55)])
+        );
     }
 
-    #[ignore]
     #[test]
-    fn bench_fib_memoized_warmed_up() {
-        let mut fib_memoized = Memoizer::new(fib);
-        let mut d: Duration = Default::default();
+    fn test_lru_bounded_recursive_memoizer_stays_correct() {
+        let mut fib_memoized = Memoizer::recursive(fib_open).with_policy(Lru::new(2));
+
+        assert_eq!(fib_memoized.call(10), 55);
+        assert_eq!(fib_memoized.call(6), 8);
+        assert!(fib_memoized.cache.store.len() <= 2);
+    }
+
+    #[test]
+    fn test_lfu_bounded_memoizer() {
+        let mut fib_memoized = Memoizer::new(fib).with_policy(Lfu::new(2));
 
+        for _ in 0..3 {
+            fib_memoized.call(10);
+        }
+        fib_memoized.call(9);
+        fib_memoized.call(8);
+
+        // 10 is kept over the less frequently used (even if more recent) 9
+        assert_eq!(fib_memoized.evictions(), 1);
+        assert_eq!(fib_memoized.cache.store, HashMap::from([(8, 21), (10, 55)]));
+    }
+
+    #[test]
+    fn test_ttl_memoizer() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let clock = ManualClock::new();
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        let mut memoized = Memoizer::new(move |n: u32| {
+            counter.set(counter.get() + 1);
+            fib(n)
+        })
+        .with_policy(Ttl::with_clock(Duration::from_secs(60), clock.clone()));
+
+        memoized.call(10);
+        clock.advance(Duration::from_secs(59));
+        memoized.call(10);
+        assert_eq!(calls.get(), 1);
+
+        // expired: evicted on lookup and recomputed
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(memoized.call(10), 55);
+        assert_eq!(calls.get(), 2);
+        assert_eq!(memoized.evictions(), 1);
+
+        // expired entries don't show up while waiting to be evicted
+        memoized.call(11);
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(memoized.len(), 2);
+        assert_eq!(memoized.iter().count(), 0);
+    }
+
+    // ballpark figures, see crate::benchmark
+    // run w/ `cargo test --release -- --ignored --show-output bench_`
+    crate::bench!(bench_fib, BenchConfig::default(), {
         for i in 0..=30 {
-            fib_memoized.call(i);
+            black_box(fib(black_box(i)));
+        }
+    });
+
+    // the first sample includes filling the cache
+    crate::bench!(
+        bench_fib_memoized_no_warmup,
+        BenchConfig::default().warmup(0),
+        Memoizer::new(fib) => |fib_memoized| {
+            for i in 0..=30 {
+                black_box(fib_memoized.call(black_box(i)));
+            }
+        }
+    );
+
+    crate::bench!(
+        bench_fib_memoized_warmed_up,
+        BenchConfig::default(),
+        Memoizer::new(fib) => |fib_memoized| {
+            for i in 0..=30 {
+                black_box(fib_memoized.call(black_box(i)));
+            }
         }
+    );
 
-        for _ in 0..1000 {
-            let start = Instant::now();
+    // fresh cache on each iteration, so this measures the intermediate-call
+    // caching alone - compare w/ bench_fib and bench_fib_memoized_no_warmup
+    crate::bench!(bench_fib_memoized_recursive_cold, BenchConfig::default(), {
+        for i in 0..=30 {
+            black_box(Memoizer::recursive(fib_open).call(black_box(i)));
+        }
+    });
+
+    crate::bench!(
+        bench_fib_memoized_recursive,
+        BenchConfig::default(),
+        Memoizer::recursive(fib_open) => |fib_memoized| {
             for i in 0..=30 {
-                fib_memoized.call(i);
+                black_box(fib_memoized.call(black_box(i)));
             }
-            d += start.elapsed();
         }
+    );
 
-        println!("{:?}", d / 1000);
+    #[test]
+    fn test_bool_funcs_are_all_of_them() {
+        let hand_written = [id_bool, negate, always_true, always_false].map(FnTable::tabulate);
+        let mut all: Vec<FnTable<bool, bool>> = FnTable::all().collect();
+        assert_eq!(count_functions::<bool, bool>(), Some(4));
+        for f in &hand_written {
+            let pos = all.iter().position(|g| g == f).unwrap();
+            all.remove(pos);
+        }
+        assert!(all.is_empty());
     }
 }
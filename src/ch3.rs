@@ -1,21 +1,445 @@
 // <https://bartoszmilewski.com/2014/12/05/categories-great-and-small/>
+//
+// A monoid is a set w/ an associative binary operation and a unit for it.
+// Rather than one dummy struct per monoid, the operation lives on the value
+// type itself, split like Haskell's Semigroup/Monoid. Types w/ more than one
+// sensible monoid (numbers: +, *, min, max; bools: ||, &&) get newtypes.
 
 #![allow(unused)]
 
-trait Monoid<T> {
-    fn mempty(v: T) -> T;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::{Add, Mul};
+use std::time::Duration;
 
-    fn mappend(v1: T, v2: T) -> T;
+// a.combine(b).combine(c) == a.combine(b.combine(c))
+pub trait Semigroup {
+    fn combine(self, other: Self) -> Self;
 }
 
-struct AddM<T>(T, T);
+// empty().combine(a) == a == a.combine(empty())
+pub trait Monoid: Semigroup {
+    fn empty() -> Self;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sum<T>(pub T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Product<T>(pub T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Min<T>(pub T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Max<T>(pub T);
+
+// leftmost/rightmost value. only semigroups, w/o a value to start from; wrap
+// in an Option for the monoid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct First<T>(pub T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Last<T>(pub T);
+
+// bools under ||
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Any(pub bool);
+
+// bools under &&
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct All(pub bool);
+
+impl<T: Add<Output = T>> Semigroup for Sum<T> {
+    fn combine(self, other: Self) -> Self {
+        Sum(self.0 + other.0)
+    }
+}
+
+impl<T: Mul<Output = T>> Semigroup for Product<T> {
+    fn combine(self, other: Self) -> Self {
+        Product(self.0 * other.0)
+    }
+}
+
+impl<T: Ord> Semigroup for Min<T> {
+    fn combine(self, other: Self) -> Self {
+        Min(self.0.min(other.0))
+    }
+}
+
+impl<T: Ord> Semigroup for Max<T> {
+    fn combine(self, other: Self) -> Self {
+        Max(self.0.max(other.0))
+    }
+}
+
+impl<T> Semigroup for First<T> {
+    fn combine(self, _: Self) -> Self {
+        self
+    }
+}
+
+impl<T> Semigroup for Last<T> {
+    fn combine(self, other: Self) -> Self {
+        other
+    }
+}
+
+impl Semigroup for Any {
+    fn combine(self, other: Self) -> Self {
+        Any(self.0 || other.0)
+    }
+}
+
+impl Monoid for Any {
+    fn empty() -> Self {
+        Any(false)
+    }
+}
+
+impl Semigroup for All {
+    fn combine(self, other: Self) -> Self {
+        All(self.0 && other.0)
+    }
+}
+
+impl Monoid for All {
+    fn empty() -> Self {
+        All(true)
+    }
+}
+
+// units of the numeric monoids
+macro_rules! numeric_monoids {
+    ($($t:ty),*) => {$(
+        impl Monoid for Sum<$t> {
+            fn empty() -> Self {
+                Sum(0 as $t)
+            }
+        }
+
+        impl Monoid for Product<$t> {
+            fn empty() -> Self {
+                Product(1 as $t)
+            }
+        }
+    )*};
+}
+
+numeric_monoids!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+// min and max only have units for bounded types
+macro_rules! bounded_monoids {
+    ($($t:ty),*) => {$(
+        impl Monoid for Min<$t> {
+            fn empty() -> Self {
+                Min(<$t>::MAX)
+            }
+        }
+
+        impl Monoid for Max<$t> {
+            fn empty() -> Self {
+                Max(<$t>::MIN)
+            }
+        }
+    )*};
+}
+
+bounded_monoids!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, char);
+
+impl Monoid for Sum<Duration> {
+    fn empty() -> Self {
+        Sum(Duration::ZERO)
+    }
+}
+
+impl Semigroup for String {
+    fn combine(mut self, other: Self) -> Self {
+        self.push_str(&other);
+        self
+    }
+}
+
+impl Monoid for String {
+    fn empty() -> Self {
+        String::new()
+    }
+}
 
-impl Monoid<u32> for AddM<u32> {
-    fn mempty(v: u32) -> u32 {
-        0
+impl<T> Semigroup for Vec<T> {
+    fn combine(mut self, mut other: Self) -> Self {
+        self.append(&mut other);
+        self
     }
+}
+
+impl<T> Monoid for Vec<T> {
+    fn empty() -> Self {
+        Vec::new()
+    }
+}
+
+// adjoins a unit (None) to any semigroup
+impl<S: Semigroup> Semigroup for Option<S> {
+    fn combine(self, other: Self) -> Self {
+        match (self, other) {
+            (Some(a), Some(b)) => Some(a.combine(b)),
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
+}
+
+impl<S: Semigroup> Monoid for Option<S> {
+    fn empty() -> Self {
+        None
+    }
+}
+
+// lexicographic: the first non-Equal wins, as in `Ordering::then`
+impl Semigroup for Ordering {
+    fn combine(self, other: Self) -> Self {
+        self.then(other)
+    }
+}
+
+impl Monoid for Ordering {
+    fn empty() -> Self {
+        Ordering::Equal
+    }
+}
+
+// union, combining the values of keys present in both
+impl<K: Eq + Hash, V: Semigroup> Semigroup for HashMap<K, V> {
+    fn combine(mut self, other: Self) -> Self {
+        for (k, v) in other {
+            let v = match self.remove(&k) {
+                Some(old) => old.combine(v),
+                None => v,
+            };
+            self.insert(k, v);
+        }
+        self
+    }
+}
+
+impl<K: Eq + Hash, V: Semigroup> Monoid for HashMap<K, V> {
+    fn empty() -> Self {
+        HashMap::new()
+    }
+}
+
+impl Semigroup for () {
+    fn combine(self, _: Self) -> Self {}
+}
+
+impl Monoid for () {
+    fn empty() -> Self {}
+}
+
+// products of monoids, componentwise
+macro_rules! tuple_monoids {
+    ($(($($t:ident $a:ident $b:ident),+))*) => {$(
+        impl<$($t: Semigroup),+> Semigroup for ($($t,)+) {
+            fn combine(self, other: Self) -> Self {
+                let ($($a,)+) = self;
+                let ($($b,)+) = other;
+                ($($a.combine($b),)+)
+            }
+        }
+
+        impl<$($t: Monoid),+> Monoid for ($($t,)+) {
+            fn empty() -> Self {
+                ($($t::empty(),)+)
+            }
+        }
+    )*};
+}
+
+tuple_monoids! {
+    (A a1 a2)
+    (A a1 a2, B b1 b2)
+    (A a1 a2, B b1 b2, C c1 c2)
+    (A a1 a2, B b1 b2, C c1 c2, D d1 d2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrary::{for_all, Arbitrary};
+    use std::fmt::Debug;
+
+    // checks the laws on values built from random `Arbitrary` inputs
+    fn check_monoid_laws<T: Arbitrary, M: Monoid + Clone + PartialEq + Debug>(
+        seed: u64,
+        wrap: impl Fn(T) -> M,
+    ) {
+        let assoc = for_all(seed, 300, |(a, b, c): (T, T, T)| {
+            let (a, b, c) = (wrap(a), wrap(b), wrap(c));
+            a.clone().combine(b.clone()).combine(c.clone()) == a.combine(b.combine(c))
+        });
+        assert!(assoc.is_ok(), "associativity: {:?}", assoc);
+
+        let unit = for_all(seed, 300, |a: T| {
+            let a = wrap(a);
+            M::empty().combine(a.clone()) == a && a.clone().combine(M::empty()) == a
+        });
+        assert!(unit.is_ok(), "identity: {:?}", unit);
+    }
+
+    #[test]
+    fn test_monoid_laws() {
+        check_monoid_laws(1, |x: u8| Sum(x as u64));
+        check_monoid_laws(2, |x: u8| Product(x as u64));
+        check_monoid_laws(3, Min::<i32>);
+        check_monoid_laws(4, Max::<u64>);
+        check_monoid_laws(5, Any);
+        check_monoid_laws(6, All);
+        check_monoid_laws(7, |x: Option<i8>| x.map(First));
+        check_monoid_laws(8, |x: Option<i8>| x.map(Last));
+        check_monoid_laws(9, |s: String| s);
+        check_monoid_laws(10, |v: Vec<u8>| v);
+        check_monoid_laws(11, |x: Option<String>| x);
+        check_monoid_laws(12, |(s, v): (String, Vec<bool>)| (s, v));
+        check_monoid_laws(13, |(a, b, c): (bool, bool, u8)| (Any(a), All(b), Max(c)));
+        check_monoid_laws(14, |x: i8| x.cmp(&0));
+        check_monoid_laws(15, |kvs: Vec<(u8, String)>| {
+            kvs.into_iter().collect::<HashMap<_, _>>()
+        });
+    }
+
+    #[test]
+    fn test_numeric_newtypes() {
+        assert_eq!(Sum(2).combine(Sum(3)), Sum(5));
+        assert_eq!(Product(2).combine(Product(3)), Product(6));
+        assert_eq!(Min(2).combine(Min(3)), Min(2));
+        assert_eq!(Max(2).combine(Max(3)), Max(3));
+        assert_eq!(Sum::<u32>::empty(), Sum(0));
+        assert_eq!(Product::<f64>::empty(), Product(1.0));
+        assert_eq!(Min::<u8>::empty(), Min(255));
+        assert_eq!(Max::<i8>::empty(), Max(-128));
+        assert_eq!(
+            Sum(Duration::from_millis(1500)).combine(Sum(Duration::from_millis(500))),
+            Sum(Duration::from_secs(2))
+        );
+    }
+
+    #[test]
+    fn test_first_last() {
+        assert_eq!(First(1).combine(First(2)), First(1));
+        assert_eq!(Last(1).combine(Last(2)), Last(2));
+        assert_eq!(None.combine(Some(First("a"))), Some(First("a")));
+        assert_eq!(Some(Last("a")).combine(None), Some(Last("a")));
+    }
+
+    #[test]
+    fn test_lexicographic_ordering() {
+        // compare by length, then alphabetically
+        let cmp = |a: &str, b: &str| a.len().cmp(&b.len()).combine(a.cmp(b));
+        assert_eq!(cmp("ab", "b"), Ordering::Greater);
+        assert_eq!(cmp("ab", "ba"), Ordering::Less);
+        assert_eq!(cmp("ab", "ab"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_hash_map_merges_values() {
+        let a = HashMap::from([("x", Sum(1)), ("y", Sum(2))]);
+        let b = HashMap::from([("y", Sum(10)), ("z", Sum(20))]);
+        assert_eq!(
+            a.combine(b),
+            HashMap::from([("x", Sum(1)), ("y", Sum(12)), ("z", Sum(20))])
+        );
+    }
+
+    #[test]
+    fn test_tuples_combine_componentwise() {
+        let stats = |x: u32| (Sum(1), Sum(x), Min(x), Max(x));
+        let (count, total, min, max) = stats(3).combine(stats(1)).combine(stats(8));
+        assert_eq!((count, total, min, max), (Sum(3), Sum(12), Min(1), Max(8)));
+        assert_eq!(<(Sum<u8>, String)>::empty(), (Sum(0), String::new()));
+    }
+
+    mod derived {
+        use crate::ch3::{Max, Min, Monoid, Semigroup, Sum};
+        use crate::monoid;
+        use std::collections::HashMap;
+
+        #[derive(Debug, Clone, PartialEq, monoid::Semigroup, monoid::Monoid)]
+        pub struct Metrics {
+            pub requests: Sum<u64>,
+            #[allow(dead_code)]
+            pub(crate) slowest: Max<u32>,
+            fastest: Min<u32>,
+            by_status: HashMap<u16, Sum<u64>>,
+            log: Vec<String>,
+        }
+
+        impl Metrics {
+            pub fn request(status: u16, millis: u32) -> Self {
+                Self {
+                    requests: Sum(1),
+                    slowest: Max(millis),
+                    fastest: Min(millis),
+                    by_status: HashMap::from([(status, Sum(1))]),
+                    log: vec![format!("{} in {}ms", status, millis)],
+                }
+            }
+        }
+
+        #[derive(Debug, PartialEq, monoid::Semigroup, monoid::Monoid)]
+        struct Pair<A, B = String>(A, B)
+        where
+            A: Clone;
+
+        #[derive(Debug, PartialEq, monoid::Semigroup, monoid::Monoid)]
+        struct Nothing;
+
+        #[derive(Debug, PartialEq, monoid::Semigroup)]
+        struct Borrowed<'a, T: Ord, const N: usize> {
+            words: Option<crate::ch3::Max<&'a str>>,
+            arrays: Vec<[T; N]>,
+        }
+
+        #[test]
+        fn test_derived_named_struct() {
+            let all = Metrics::request(200, 12)
+                .combine(Metrics::request(500, 80))
+                .combine(Metrics::request(200, 7));
+            assert_eq!(all.requests, Sum(3));
+            assert_eq!((all.fastest, all.slowest), (Min(7), Max(80)));
+            assert_eq!(all.by_status[&200], Sum(2));
+            assert_eq!(all.log, ["200 in 12ms", "500 in 80ms", "200 in 7ms"]);
+
+            let empty = Metrics::empty();
+            assert_eq!(empty.requests, Sum(0));
+            assert!(empty.by_status.is_empty());
+            assert_eq!(Metrics::empty().combine(all.clone()), all);
+        }
+
+        #[test]
+        fn test_derived_generic_tuple_and_unit_structs() {
+            let p = Pair(Sum(1), "a".to_string()).combine(Pair(Sum(2), "b".to_string()));
+            assert_eq!(p, Pair(Sum(3), "ab".to_string()));
+            assert_eq!(Pair::<Vec<u8>>::empty(), Pair(vec![], String::new()));
+            assert_eq!(Nothing.combine(Nothing), Nothing::empty());
 
-    fn mappend(v1: u32, v2: u32) -> u32 {
-        v1 + v2
+            let b = Borrowed {
+                words: Some(Max("a")),
+                arrays: vec![[1, 2]],
+            };
+            let c = Borrowed {
+                words: Some(Max("b")),
+                arrays: vec![[3, 4]],
+            };
+            assert_eq!(
+                b.combine(c),
+                Borrowed {
+                    words: Some(Max("b")),
+                    arrays: vec![[1, 2], [3, 4]],
+                }
+            );
+        }
     }
 }
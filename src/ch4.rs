@@ -2,31 +2,33 @@
 // 1. Kleisli cat for partial funcs
 // 2. impl safe_reciprocal()
 // 3. compose safe_root() and safe_reciprocal()
-// the writer mod done as an extra exercise that sticks to the matching
-// Haskell code in the post - it's neither v. idiomatic nor efficient Rust.
+// the writer mod started as an extra exercise sticking to the matching
+// Haskell code in the post; it's since been generalized to any monoid log,
+// w/ `Chunks` for deeply nested chains where copying strings around would be
+// slow.
 
 #![allow(unused)]
 
 pub mod optional {
-    fn identity_morphism<T>(v: T) -> Option<T> {
+    pub fn identity_morphism<T>(v: T) -> Option<T> {
         Some(v)
     }
 
-    fn compose<T, U>(
+    pub fn compose<T, U, V>(
         f: impl Fn(T) -> Option<U>,
-        g: impl Fn(U) -> Option<U>,
-    ) -> impl Fn(T) -> Option<U> {
+        g: impl Fn(U) -> Option<V>,
+    ) -> impl Fn(T) -> Option<V> {
         move |x| f(x).and_then(&g)
     }
 
-    fn safe_root(arg: f64) -> Option<f64> {
+    pub fn safe_root(arg: f64) -> Option<f64> {
         match arg {
             _ if arg >= 0.0 => Some(arg.sqrt()),
             _ => None,
         }
     }
 
-    fn safe_reciprocal(arg: f64) -> Option<f64> {
+    pub fn safe_reciprocal(arg: f64) -> Option<f64> {
         match arg {
             _ if arg != 0.0 => Some(1.0 / arg),
             _ => None,
@@ -39,21 +41,112 @@ pub mod optional {
 }
 
 pub mod writer {
-    #[derive(Debug, PartialEq)]
-    pub struct Writer<T>(pub T, pub String);
+    use std::collections::VecDeque;
+
+    use crate::ch3::{Monoid, Semigroup};
+    use crate::monoid_fold::MonoidIterator;
 
-    pub fn identity_morphism<T>(v: T) -> Writer<T> {
-        Writer(v, "".to_string())
+    // a value along w/ a log, which can be any monoid: String (the default, as
+    // in the post), Vec<Event>, Sum<u32> counters, Sum<Duration> timings...
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Writer<T, W = String>(pub T, pub W);
+
+    pub fn identity_morphism<T, W: Monoid>(v: T) -> Writer<T, W> {
+        Writer(v, W::empty())
     }
 
-    pub fn compose<T, U, V>(
-        f: impl Fn(T) -> Writer<U>,
-        g: impl Fn(U) -> Writer<V>,
-    ) -> impl Fn(T) -> Writer<V> {
-        move |x| {
-            let Writer(y, s1) = f(x);
-            let Writer(z, s2) = g(y);
-            Writer(z, s1 + &s2)
+    pub fn compose<T, U, V, W: Monoid>(
+        f: impl Fn(T) -> Writer<U, W>,
+        g: impl Fn(U) -> Writer<V, W>,
+    ) -> impl Fn(T) -> Writer<V, W> {
+        move |x| f(x).and_then(&g)
+    }
+
+    impl<W: Monoid> Writer<(), W> {
+        // just a log entry
+        pub fn tell(w: W) -> Self {
+            Writer((), w)
+        }
+    }
+
+    impl<T, W: Monoid> Writer<T, W> {
+        pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Writer<U, W> {
+            Writer(f(self.0), self.1)
+        }
+
+        // run f on the value, appending its log to ours
+        pub fn and_then<U>(self, f: impl FnOnce(T) -> Writer<U, W>) -> Writer<U, W> {
+            let Writer(u, w) = f(self.0);
+            Writer(u, self.1.combine(w))
+        }
+
+        // the log so far, as part of the value too
+        pub fn listen(self) -> Writer<(T, W), W>
+        where
+            W: Clone,
+        {
+            let Writer(v, w) = self;
+            Writer((v, w.clone()), w)
+        }
+
+        // rewrite the log so far
+        pub fn censor(self, f: impl FnOnce(W) -> W) -> Self {
+            Writer(self.0, f(self.1))
+        }
+    }
+
+    // a log kept as a list of pieces, joined once at the end. Appending to a
+    // String copies it, so a long chain of binds nested to the right (f >=>
+    // (g >=> (h >=> ...))) takes quadratic time; here combining two logs
+    // moves the smaller one's pieces onto the larger one, so each piece is
+    // moved O(log n) times whatever the nesting. That fixes the copying, not
+    // the depth: running such a chain still recurses once per level, so its
+    // length stays bounded by the stack (a few thousand binds on a test thread).
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Chunks<W>(VecDeque<W>);
+
+    impl<W> Chunks<W> {
+        pub fn of(w: W) -> Self {
+            Chunks(VecDeque::from([w]))
+        }
+
+        // number of pieces
+        pub fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.0.is_empty()
+        }
+
+        pub fn iter(&self) -> impl Iterator<Item = &W> {
+            self.0.iter()
+        }
+    }
+
+    impl<W: Monoid> Chunks<W> {
+        pub fn concat(self) -> W {
+            self.0.into_iter().mconcat()
+        }
+    }
+
+    impl<W> Semigroup for Chunks<W> {
+        fn combine(mut self, mut other: Self) -> Self {
+            if self.0.len() >= other.0.len() {
+                self.0.append(&mut other.0);
+                self
+            } else {
+                while let Some(w) = self.0.pop_back() {
+                    other.0.push_front(w);
+                }
+                other
+            }
+        }
+    }
+
+    impl<W> Monoid for Chunks<W> {
+        fn empty() -> Self {
+            Chunks(VecDeque::new())
         }
     }
 
@@ -81,8 +174,11 @@ pub mod writer {
 
 #[cfg(test)]
 mod tests {
-    use super::optional::*;
+    use super::optional::safe_root_reciprocal;
     use super::writer::*;
+    use crate::benchmark::BenchConfig;
+    use crate::ch3::{Monoid, Semigroup, Sum};
+    use std::time::Duration;
 
     #[test]
     fn test_upcase_to_words() {
@@ -110,4 +206,108 @@ mod tests {
     fn test_safe_root_reciprocal_positive_number() {
         assert_eq!(safe_root_reciprocal(4.0), Some(0.5));
     }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Event {
+        Parsed(u32),
+        Rejected(String),
+    }
+
+    fn parse(s: &str) -> Writer<Option<u32>, Vec<Event>> {
+        match s.parse() {
+            Ok(n) => Writer(Some(n), vec![Event::Parsed(n)]),
+            Err(_) => Writer(None, vec![Event::Rejected(s.to_string())]),
+        }
+    }
+
+    #[test]
+    fn test_structured_log() {
+        let total = ["1", "two", "3"].into_iter().fold(
+            identity_morphism(0),
+            |acc: Writer<u32, Vec<Event>>, s| {
+                acc.and_then(|sum| parse(s).map(|n| sum + n.unwrap_or(0)))
+            },
+        );
+        assert_eq!(total.0, 4);
+        assert_eq!(
+            total.1,
+            [
+                Event::Parsed(1),
+                Event::Rejected("two".to_string()),
+                Event::Parsed(3)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_counter_and_duration_logs() {
+        let step = |x: u64| Writer(x * 3, Sum(1u32));
+        let three_steps = compose(compose(step, step), step);
+        assert_eq!(three_steps(1), Writer(27, Sum(3)));
+
+        let timed = |ms: u64| Writer(ms, Sum(Duration::from_millis(ms)));
+        let both = compose(timed, |ms| timed(ms * 2));
+        assert_eq!(both(100), Writer(200, Sum(Duration::from_millis(300))));
+    }
+
+    #[test]
+    fn test_tell_listen_censor() {
+        let w = Writer::tell("start ".to_string())
+            .and_then(|()| upcase("hi".to_string()))
+            .and_then(to_words)
+            .listen()
+            .map(|(words, log)| (words.len(), log.len()));
+        assert_eq!(w, Writer((1, 22), "start upcase to_words ".to_string()));
+
+        let quiet = upcase("shh".to_string()).censor(|_| String::new());
+        assert_eq!(quiet.1, "");
+    }
+
+    #[test]
+    fn test_chunks_keep_order() {
+        let chunk = |s: &str| Chunks::of(s.to_string());
+        // larger on either side
+        let left = chunk("a").combine(chunk("b")).combine(chunk("c"));
+        let right = chunk("a").combine(chunk("b").combine(chunk("c")));
+        assert_eq!(left.len(), 3);
+        assert_eq!(left.clone().concat(), "abc");
+        assert_eq!(right.concat(), "abc");
+        assert!(Chunks::<String>::empty().is_empty());
+        assert_eq!(Chunks::empty().combine(left).concat(), "abc");
+    }
+
+    // deep enough for the quadratic String log to show, shallow enough for a
+    // debug build's recursion through the chain to fit a test thread's stack
+    const NESTING: usize = 2000;
+
+    // n steps, composed right-nested: step >=> (step >=> (... >=> step))
+    fn right_nested<W: Monoid + 'static>(
+        n: usize,
+        log: impl Fn(u32) -> W + Copy + 'static,
+    ) -> Box<dyn Fn(u32) -> Writer<u32, W>> {
+        let step = move |x: u32| Writer(x + 1, log(x));
+        (1..n).fold(Box::new(step), |rest, _| Box::new(compose(step, rest)))
+    }
+
+    #[test]
+    fn test_long_right_nested_chain() {
+        let Writer(n, log) = right_nested(NESTING, |x| Chunks::of(format!("{} ", x)))(0);
+        assert_eq!(n as usize, NESTING);
+        assert_eq!(log.len(), NESTING);
+        let log = log.concat();
+        assert!(log.starts_with("0 1 2 "));
+        assert!(log.ends_with(" 1998 1999 "));
+    }
+
+    crate::bench!(
+        bench_right_nested_string_log,
+        BenchConfig::default().samples(20),
+        right_nested(NESTING, |_| "step ".to_string()) => |chain| { chain(0) }
+    );
+
+    crate::bench!(
+        bench_right_nested_chunked_log,
+        BenchConfig::default().samples(20),
+        right_nested(NESTING, |_| Chunks::of("step ".to_string())) => |chain| { chain(0).1.concat() }
+    );
 }
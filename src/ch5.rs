@@ -4,7 +4,8 @@
 
 #![allow(unused)]
 
-enum Either<T, U> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Either<T, U> {
     Left(T),
     Right(U),
 }
@@ -4,14 +4,15 @@
 
 #![allow(unused)]
 
-enum Shape {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
     Circle { r: f64 },
     Rectangle { d: f64, h: f64 },
     Square { s: f64 },
 }
 
 impl Shape {
-    fn area(&self) -> f64 {
+    pub fn area(&self) -> f64 {
         match self {
             Self::Circle { r } => std::f64::consts::PI * r * r,
             Self::Rectangle { d, h } => d * h,
@@ -19,7 +20,7 @@ impl Shape {
         }
     }
 
-    fn perim(&self) -> f64 {
+    pub fn perim(&self) -> f64 {
         match self {
             Self::Circle { r } => 2.0 * std::f64::consts::PI * r,
             Self::Rectangle { d, h } => 2.0 * (d + h),
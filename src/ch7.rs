@@ -3,14 +3,14 @@
 
 #![allow(unused)]
 
-fn reader_functor<T, U, V>(f: impl Fn(T) -> U, g: impl Fn(V) -> T) -> impl FnMut(V) -> U {
+pub fn reader_functor<T, U, V>(f: impl Fn(T) -> U, g: impl Fn(V) -> T) -> impl FnMut(V) -> U {
     move |v: V| f(g(v))
 }
 
 // RPITIT `impl GatLessFunctor` is opaque - we can't do much with it.
 // By extension we can't do much w/ GatLessFunctor, though the code
 // below compiles just fine.
-trait GatLessFunctor<T> {
+pub trait GatLessFunctor<T> {
     fn gf_fmap<U>(self, f: impl Fn(T) -> U) -> impl GatLessFunctor<U>;
 }
 
@@ -21,7 +21,7 @@ impl<T> GatLessFunctor<T> for Option<T> {
 }
 
 // F could (should?) be FnMut instead of Fn
-trait Functor {
+pub trait Functor {
     type InnerSource;
     type OuterTarget<U>: Functor;
 
@@ -3,7 +3,10 @@
 
 #![allow(unused)]
 
-trait Bifunctor {
+// reuse ch5's Either rather than redefining it here
+use crate::ch5::Either;
+
+pub trait Bifunctor {
     type InnerFirst;
     type InnerSecond;
     type OuterTarget<V, W>: Bifunctor;
@@ -38,12 +41,6 @@ trait Bifunctor {
         Self: Sized;
 }
 
-#[derive(Debug, PartialEq)]
-enum Either<T, U> {
-    Left(T),
-    Right(U),
-}
-
 impl<T, U> Bifunctor for Either<T, U> {
     type InnerFirst = T;
     type InnerSecond = U;
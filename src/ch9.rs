@@ -1,11 +1,11 @@
 #![allow(unused)]
 
-fn curry<T: Clone, U, V, F: Fn(T, U) -> V + Clone>(f: F, t: T) -> impl Fn(U) -> V {
+pub fn curry<T: Clone, U, V, F: Fn(T, U) -> V + Clone>(f: F, t: T) -> impl Fn(U) -> V {
     let f = f.clone();
     move |u: U| f(t.clone(), u)
 }
 
-fn uncurry<T: Clone, U: Clone, V, F: Fn(T, U) -> V + Clone>(f: F) -> impl Fn((T, U)) -> V {
+pub fn uncurry<T: Clone, U: Clone, V, F: Fn(T, U) -> V + Clone>(f: F) -> impl Fn((T, U)) -> V {
     let f = f.clone();
     move |(t, u)| f(t, u)
 }
@@ -4,7 +4,7 @@
 
 #![allow(unused)]
 
-trait IsoCurry<T, U, V> {
+pub trait IsoCurry<T, U, V> {
     fn curry(&self, t: T) -> impl Fn(U) -> V;
     fn curry_once(self, t: T) -> impl FnOnce(U) -> V;
 
@@ -36,15 +36,45 @@ where
     }
 }
 
+// n-ary counterpart of `uncurry`, for 2 to 6 arguments: call a function on
+// a tuple of its arguments
+pub trait Tupled<Args, V> {
+    fn call_tupled(&self, args: Args) -> V;
+}
+
+macro_rules! impl_tupled {
+    ($(($($t:ident $a:ident),+)),*) => {
+        $(
+            impl<$($t,)+ V, F> Tupled<($($t,)+), V> for F
+            where
+                F: Fn($($t),+) -> V,
+            {
+                fn call_tupled(&self, ($($a,)+): ($($t,)+)) -> V {
+                    self($($a),+)
+                }
+            }
+        )*
+    };
+}
+
+impl_tupled!(
+    (A a, B b),
+    (A a, B b, C c),
+    (A a, B b, C c, D d),
+    (A a, B b, C c, D d, E e),
+    (A a, B b, C c, D d, E e, G g)
+);
+
+// ch9's running example, also memoized in ch2
+pub(crate) fn count_char(s: &str, c: char) -> usize {
+    s.chars().filter(|ch| ch == &c).count()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::ops::Add;
 
-    fn count_char(s: &str, c: char) -> usize {
-        s.chars().filter(|ch| ch == &c).count()
-    }
-
     #[test]
     fn test_curry() {
         let add = |x: u32, y: u32| x + y;
@@ -93,6 +123,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tupled() {
+        assert_eq!(count_char.call_tupled(("element", 'e')), 3);
+
+        let add3 = |x: u32, y: u32, z: u32| x + y + z;
+        assert_eq!(add3.call_tupled((1, 2, 3)), 6);
+
+        let concat6 =
+            |a: &str, b: &str, c: &str, d: &str, e: &str, f: &str| [a, b, c, d, e, f].concat();
+        assert_eq!(
+            concat6.call_tupled(("a", "b", "c", "d", "e", "f")),
+            "abcdef"
+        );
+    }
+
     #[test]
     fn test_uncurry_once() {
         let add = |x: u32, y: u32| x + y;
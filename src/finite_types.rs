@@ -0,0 +1,377 @@
+// Finite types, i.e. types whose values can all be listed, and the (also
+// finite) sets of total functions between them, after ch2's challenge of
+// writing out every Bool -> Bool function by hand.
+//
+// A function A -> B between finite types is fully described by its lookup
+// table: B's value for each of A's values, in `Finite::elements` order.
+// There are |B|^|A| such tables, and two functions are extensionally equal
+// iff they have the same table.
+
+#![allow(unused)]
+
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use crate::ch1::{compose, id};
+use crate::ch5::Either;
+
+pub trait Finite: Sized + Clone + PartialEq {
+    // every value of the type, each exactly once, always in the same order
+    fn elements() -> Vec<Self>;
+
+    fn cardinality() -> usize {
+        Self::elements().len()
+    }
+
+    // position of `self` in `elements()`
+    fn index(&self) -> usize {
+        Self::elements()
+            .iter()
+            .position(|x| x == self)
+            .expect("Finite::elements is missing a value")
+    }
+}
+
+// implement `Finite` for fieldless enums by listing their variants:
+//   impl_finite!(Color: Red, Green, Blue);
+#[macro_export]
+macro_rules! impl_finite {
+    ($t:ident: $($variant:ident),+ $(,)?) => {
+        impl $crate::finite::Finite for $t {
+            fn elements() -> Vec<Self> {
+                vec![$($t::$variant),+]
+            }
+        }
+    };
+}
+
+impl Finite for () {
+    fn elements() -> Vec<Self> {
+        vec![()]
+    }
+}
+
+impl Finite for bool {
+    fn elements() -> Vec<Self> {
+        vec![false, true]
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl_finite!(Ordering: Less, Equal, Greater);
+
+impl<T: Finite> Finite for Option<T> {
+    fn elements() -> Vec<Self> {
+        std::iter::once(None)
+            .chain(T::elements().into_iter().map(Some))
+            .collect()
+    }
+}
+
+// |Either<T, U>| = |T| + |U|
+impl<T: Finite, U: Finite> Finite for Either<T, U> {
+    fn elements() -> Vec<Self> {
+        T::elements()
+            .into_iter()
+            .map(Either::Left)
+            .chain(U::elements().into_iter().map(Either::Right))
+            .collect()
+    }
+}
+
+impl<T: Finite, E: Finite> Finite for Result<T, E> {
+    fn elements() -> Vec<Self> {
+        T::elements()
+            .into_iter()
+            .map(Ok)
+            .chain(E::elements().into_iter().map(Err))
+            .collect()
+    }
+}
+
+// |(T, U)| = |T| * |U|
+impl<T: Finite, U: Finite> Finite for (T, U) {
+    fn elements() -> Vec<Self> {
+        let us = U::elements();
+        T::elements()
+            .into_iter()
+            .flat_map(|t| us.iter().map(move |u| (t.clone(), u.clone())))
+            .collect()
+    }
+}
+
+impl<T: Finite, U: Finite, V: Finite> Finite for (T, U, V) {
+    fn elements() -> Vec<Self> {
+        <(T, (U, V))>::elements()
+            .into_iter()
+            .map(|(t, (u, v))| (t, u, v))
+            .collect()
+    }
+}
+
+// |B|^|A|, or None if that doesn't fit
+pub fn count_functions<A: Finite, B: Finite>() -> Option<u128> {
+    let exp = u32::try_from(A::cardinality()).ok()?;
+    (B::cardinality() as u128).checked_pow(exp)
+}
+
+// first input on which `f` and `g` disagree, if any
+pub fn find_difference<A: Finite, B: PartialEq>(
+    f: impl Fn(A) -> B,
+    g: impl Fn(A) -> B,
+) -> Option<A> {
+    A::elements()
+        .into_iter()
+        .find(|a| f(a.clone()) != g(a.clone()))
+}
+
+// extensional equality: same output for every input, checked exhaustively
+pub fn ext_eq<A: Finite, B: PartialEq>(f: impl Fn(A) -> B, g: impl Fn(A) -> B) -> bool {
+    find_difference(f, g).is_none()
+}
+
+// whether `g` is the inverse of `f`, i.e. g . f = id and f . g = id
+pub fn is_isomorphism<A: Finite, B: Finite>(f: impl Fn(A) -> B, g: impl Fn(B) -> A) -> bool {
+    ext_eq(compose(&f, &g), id) && ext_eq(compose(&g, &f), id)
+}
+
+// a total function A -> B as a lookup table; equal tables are extensionally
+// equal functions
+#[derive(Clone)]
+pub struct FnTable<A, B> {
+    // outputs[i] = f(A::elements()[i])
+    outputs: Vec<B>,
+    _domain: PhantomData<fn(A)>,
+}
+
+impl<A: Finite, B: Finite> FnTable<A, B> {
+    pub fn tabulate(f: impl Fn(A) -> B) -> Self {
+        Self {
+            outputs: A::elements().into_iter().map(f).collect(),
+            _domain: PhantomData,
+        }
+    }
+
+    pub fn apply(&self, a: &A) -> B {
+        self.outputs[a.index()].clone()
+    }
+
+    // back to a plain function
+    pub fn into_fn(self) -> impl Fn(A) -> B {
+        move |a| self.apply(&a)
+    }
+
+    // (input, output) pairs, in domain order
+    pub fn graph(&self) -> impl Iterator<Item = (A, &B)> {
+        A::elements().into_iter().zip(self.outputs.iter())
+    }
+
+    // every total function A -> B, counting in base |B| w/ one digit per
+    // element of A (so the first one maps everything to B's first element)
+    pub fn all() -> impl Iterator<Item = Self> {
+        let codomain = B::elements();
+        let (n, k) = (A::cardinality(), codomain.len());
+        // w/ an empty codomain there are no functions, unless the domain is
+        // empty too, in which case there's exactly one
+        let mut digits = (k > 0 || n == 0).then(|| vec![0; n]);
+        std::iter::from_fn(move || {
+            let current = digits.as_mut()?;
+            let table = FnTable {
+                outputs: current.iter().map(|&d| codomain[d].clone()).collect(),
+                _domain: PhantomData,
+            };
+            // increment, w/ carry; wrapping all the way round means we're done
+            let mut i = 0;
+            loop {
+                if i == current.len() {
+                    digits = None;
+                    break;
+                }
+                current[i] += 1;
+                if current[i] < k {
+                    break;
+                }
+                current[i] = 0;
+                i += 1;
+            }
+            Some(table)
+        })
+    }
+}
+
+// by hand, as derive would needlessly require A: PartialEq etc.
+impl<A, B: PartialEq> PartialEq for FnTable<A, B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.outputs == other.outputs
+    }
+}
+
+impl<A, B: Eq> Eq for FnTable<A, B> {}
+
+impl<A, B: Hash> Hash for FnTable<A, B> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.outputs.hash(state);
+    }
+}
+
+impl<A: Finite + Debug, B: Finite + Debug> Debug for FnTable<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.graph()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Color {
+        Red,
+        Green,
+        Blue,
+    }
+
+    impl_finite!(Color: Red, Green, Blue);
+
+    #[test]
+    fn test_elements_and_cardinalities() {
+        assert_eq!(<()>::cardinality(), 1);
+        assert_eq!(bool::elements(), vec![false, true]);
+        assert_eq!(
+            Color::elements(),
+            vec![Color::Red, Color::Green, Color::Blue]
+        );
+        assert_eq!(Option::<Ordering>::cardinality(), 4);
+        assert_eq!(Either::<bool, Color>::cardinality(), 5);
+        assert_eq!(Result::<(), bool>::cardinality(), 3);
+        assert_eq!(<(bool, Color)>::cardinality(), 6);
+        assert_eq!(<(bool, bool, Color)>::cardinality(), 12);
+    }
+
+    #[test]
+    fn test_index_matches_elements() {
+        for (i, x) in <(Option<bool>, Either<Color, ()>)>::elements()
+            .iter()
+            .enumerate()
+        {
+            assert_eq!(x.index(), i);
+        }
+    }
+
+    #[test]
+    fn test_enumerate_all_functions() {
+        fn check<A: Finite, B: Finite + Eq + Hash>() {
+            let all: Vec<FnTable<A, B>> = FnTable::all().collect();
+            assert_eq!(Some(all.len() as u128), count_functions::<A, B>());
+            // all distinct
+            let unique: HashSet<_> = all.iter().collect();
+            assert_eq!(unique.len(), all.len());
+        }
+
+        check::<bool, bool>();
+        check::<Ordering, bool>();
+        check::<bool, Ordering>();
+        check::<Color, Option<bool>>();
+        check::<Either<bool, ()>, Color>();
+        check::<(), Color>();
+        check::<Color, ()>();
+        assert_eq!(count_functions::<Either<bool, ()>, Color>(), Some(27));
+        assert_eq!(count_functions::<Color, Option<Ordering>>(), Some(64));
+    }
+
+    #[test]
+    fn test_extensional_equality_of_tables() {
+        // different definitions, same function
+        let xor = FnTable::tabulate(|(a, b): (bool, bool)| a ^ b);
+        let neq = FnTable::tabulate(|(a, b): (bool, bool)| a != b);
+        let or = FnTable::tabulate(|(a, b): (bool, bool)| a || b);
+        assert_eq!(xor, neq);
+        assert_ne!(xor, or);
+
+        let cmp = FnTable::tabulate(|(a, b): (bool, bool)| a.cmp(&b));
+        assert_eq!(cmp.apply(&(false, true)), Ordering::Less);
+        assert_eq!(
+            format!("{:?}", FnTable::tabulate(|c: Color| c == Color::Green)),
+            "{Red: false, Green: true, Blue: false}"
+        );
+
+        let f = cmp.clone().into_fn();
+        assert_eq!(FnTable::tabulate(f), cmp);
+    }
+
+    #[test]
+    fn test_ext_eq() {
+        assert!(ext_eq(compose(|b: bool| !b, |b: bool| !b), id));
+        assert!(ext_eq(|(a, b): (bool, bool)| a && b, |(a, b)| !(!a || !b)));
+        assert!(!ext_eq(|(a, b): (bool, bool)| a && b, |(a, b)| a || b));
+        assert_eq!(
+            find_difference(|(a, b): (bool, bool)| a && b, |(a, b)| a || b),
+            Some((false, true))
+        );
+        assert_eq!(
+            find_difference(|o: Ordering| o.reverse().reverse(), id),
+            None
+        );
+    }
+
+    #[test]
+    fn test_either_unit_unit_is_bool() {
+        let to_bool = |e: Either<(), ()>| matches!(e, Either::Right(()));
+        let from_bool = |b: bool| {
+            if b {
+                Either::Right(())
+            } else {
+                Either::Left(())
+            }
+        };
+        assert!(is_isomorphism(to_bool, from_bool));
+        // so is the swapped one
+        assert!(is_isomorphism(|e| !to_bool(e), |b: bool| from_bool(!b)));
+    }
+
+    #[test]
+    fn test_small_isomorphisms() {
+        // Option<()> = 1 + 1 = bool
+        assert!(is_isomorphism(
+            |o: Option<()>| o.is_some(),
+            |b: bool| b.then_some(())
+        ));
+
+        // Option<bool> = 1 + 2 = Ordering
+        let to_ordering = |o: Option<bool>| match o {
+            None => Ordering::Equal,
+            Some(false) => Ordering::Less,
+            Some(true) => Ordering::Greater,
+        };
+        let from_ordering = |o: Ordering| match o {
+            Ordering::Equal => None,
+            o => Some(o.is_gt()),
+        };
+        assert!(is_isomorphism(to_ordering, from_ordering));
+
+        // (bool, ()) = bool * 1 = bool
+        assert!(is_isomorphism(|(b, ()): (bool, ())| b, |b: bool| (b, ())));
+
+        // (T, U) = (U, T)
+        let swap = |(b, o): (bool, Ordering)| (o, b);
+        let swap_back = |(o, b): (Ordering, bool)| (b, o);
+        assert!(is_isomorphism(swap, swap_back));
+    }
+
+    #[test]
+    fn test_not_isomorphisms() {
+        // g . f = id, but f isn't onto
+        let embed = |b: bool| if b { Ordering::Greater } else { Ordering::Less };
+        let project = |o: Ordering| o.is_gt();
+        assert!(!is_isomorphism(embed, project));
+
+        // neither way round
+        assert!(!is_isomorphism(|b: bool| b, |b: bool| !b));
+    }
+}
@@ -0,0 +1,205 @@
+// <https://bartoszmilewski.com/2015/07/21/free-monoids/>
+//
+// The free monoid on A is lists of A under concatenation: nothing holds in it
+// but the monoid laws. Its universal property: any function A -> M into a
+// monoid extends to exactly one monoid homomorphism FreeMonoid<A> -> M, the
+// one that maps each generator w/ f and combines the results (`fold_into`).
+//
+// A homomorphism h: M -> N preserves the structure:
+//   h(a <> b) == h(a) <> h(b)   and   h(empty) == empty
+// which `check_monoid_homomorphism` checks pointwise over sample inputs.
+
+#![allow(unused)]
+
+use std::fmt::Debug;
+
+use crate::category_laws::{check_pointwise, Law, LawViolation};
+use crate::ch3::{Monoid, Semigroup};
+use crate::monoid_fold::MonoidIterator;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FreeMonoid<A>(Vec<A>);
+
+impl<A> FreeMonoid<A> {
+    // the generator `a`, as a one-element word
+    pub fn of(a: A) -> Self {
+        FreeMonoid(vec![a])
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, A> {
+        self.0.iter()
+    }
+
+    pub fn into_vec(self) -> Vec<A> {
+        self.0
+    }
+
+    // the unique homomorphism extending `f`
+    pub fn fold_into<M: Monoid>(self, f: impl FnMut(A) -> M) -> M {
+        self.0.into_iter().fold_map(f)
+    }
+
+    // FreeMonoid is a functor: relabel the generators
+    pub fn map<B>(self, f: impl FnMut(A) -> B) -> FreeMonoid<B> {
+        FreeMonoid(self.0.into_iter().map(f).collect())
+    }
+}
+
+impl<A> Semigroup for FreeMonoid<A> {
+    fn combine(self, other: Self) -> Self {
+        FreeMonoid(self.0.combine(other.0))
+    }
+}
+
+impl<A> Monoid for FreeMonoid<A> {
+    fn empty() -> Self {
+        FreeMonoid(Vec::new())
+    }
+}
+
+impl<A> From<Vec<A>> for FreeMonoid<A> {
+    fn from(xs: Vec<A>) -> Self {
+        FreeMonoid(xs)
+    }
+}
+
+impl<A> FromIterator<A> for FreeMonoid<A> {
+    fn from_iter<I: IntoIterator<Item = A>>(iter: I) -> Self {
+        FreeMonoid(iter.into_iter().collect())
+    }
+}
+
+impl<A> IntoIterator for FreeMonoid<A> {
+    type Item = A;
+    type IntoIter = std::vec::IntoIter<A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+// checks `h` preserves empty, then combine for each sample pair
+pub fn check_monoid_homomorphism<M, N>(
+    h: impl Fn(M) -> N,
+    samples: impl IntoIterator<Item = (M, M)>,
+) -> Result<(), LawViolation>
+where
+    M: Monoid + Clone + Debug,
+    N: Monoid + PartialEq + Debug,
+{
+    check_pointwise(
+        Law::PreservesEmpty,
+        |()| h(M::empty()),
+        |()| N::empty(),
+        [()],
+    )?;
+    check_pointwise(
+        Law::PreservesCombine,
+        |(a, b): (M, M)| h(a.combine(b)),
+        |(a, b)| h(a).combine(h(b)),
+        samples,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrary::samples;
+    use crate::ch3::{Any, Max, Product, Sum};
+
+    fn word(s: &str) -> FreeMonoid<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_free_monoid_is_concatenation() {
+        assert_eq!(word("ab").combine(word("cd")), word("abcd"));
+        assert_eq!(FreeMonoid::empty().combine(word("ab")), word("ab"));
+        assert_eq!(FreeMonoid::of('a').len(), 1);
+        assert!(FreeMonoid::<u8>::empty().is_empty());
+    }
+
+    #[test]
+    fn test_fold_into_extends_generators() {
+        // fold_into(f) agrees w/ f on the generators...
+        let f = |c: char| Sum(c as u32);
+        assert_eq!(FreeMonoid::of('a').fold_into(f), f('a'));
+        // ... and so is determined by them
+        assert_eq!(word("abc").fold_into(f), Sum(97 + 98 + 99));
+        assert_eq!(word("").fold_into(f), Sum(0));
+
+        assert_eq!(word("hello").fold_into(|_| Sum(1)), Sum(5));
+        assert_eq!(word("hello").fold_into(|c| Any(c == 'l')), Any(true));
+        assert_eq!(
+            FreeMonoid::from(vec![2, 3, 7]).fold_into(Product),
+            Product(42)
+        );
+        assert_eq!(word("ab").map(|c| c.to_ascii_uppercase()), word("AB"));
+    }
+
+    #[test]
+    fn test_fold_into_is_a_homomorphism() {
+        let pairs: Vec<(FreeMonoid<u8>, FreeMonoid<u8>)> = samples::<(Vec<u8>, Vec<u8>)>(7, 200)
+            .into_iter()
+            .map(|(a, b)| (a.into(), b.into()))
+            .collect();
+
+        let results = [
+            check_monoid_homomorphism(
+                |w: FreeMonoid<u8>| w.fold_into(|x| Sum(x as u64)),
+                pairs.clone(),
+            ),
+            check_monoid_homomorphism(
+                |w: FreeMonoid<u8>| w.fold_into(|x| Some(Max(x))),
+                pairs.clone(),
+            ),
+            check_monoid_homomorphism(|w: FreeMonoid<u8>| w.fold_into(|x| x.to_string()), pairs),
+        ];
+        assert!(results.iter().all(Result::is_ok), "{:?}", results);
+    }
+
+    #[test]
+    fn test_string_homomorphisms() {
+        let pairs = samples::<(String, String)>(11, 200);
+
+        // String is the free monoid on chars (up to isomorphism)
+        assert_eq!(
+            check_monoid_homomorphism(|s: String| word(&s), pairs.clone()),
+            Ok(())
+        );
+        assert_eq!(
+            check_monoid_homomorphism(|s: String| Sum(s.chars().count()), pairs.clone()),
+            Ok(())
+        );
+        assert_eq!(
+            check_monoid_homomorphism(|s: String| s.to_uppercase(), pairs),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_non_homomorphisms_are_caught() {
+        // preserves empty but not combine
+        let violation = check_monoid_homomorphism(
+            |s: String| Max(s.len()),
+            [("ab".to_string(), "c".to_string())],
+        )
+        .unwrap_err();
+        assert_eq!(violation.law, Law::PreservesCombine);
+        assert_eq!(violation.lhs, "Max(3)");
+        assert_eq!(violation.rhs, "Max(2)");
+
+        // doesn't even preserve empty
+        let violation = check_monoid_homomorphism(|s: String| Sum(s.len() + 1), []).unwrap_err();
+        assert_eq!(violation.law, Law::PreservesEmpty);
+        assert_eq!(violation.input, "()");
+    }
+}
@@ -1,3 +1,13 @@
+// lets the derives' `::ctfp::...` paths resolve inside this crate too
+extern crate self as ctfp;
+
+// Chapter modules hold the exercises as written while following the book.
+// They stay private: downstream code goes through the facade modules below,
+// which re-export the reusable bits under stable, topic-based paths.
+mod arbitrary;
+mod benchmark;
+mod categories;
+mod category_laws;
 mod ch1;
 mod ch10;
 mod ch2;
@@ -9,4 +19,143 @@ mod ch7;
 mod ch8;
 mod ch9;
 mod currying_alt;
+mod finite_types;
+mod free_monoid;
 mod memoize_alt;
+mod memoize_async;
+mod memoize_eviction;
+mod memoize_fallible;
+mod memoize_multi;
+mod memoize_persist;
+mod memoize_sync;
+mod monad;
+mod monoid_category;
+mod monoid_fold;
+mod pipeline;
+mod span_writer;
+
+// identity and composition of plain functions, and the `Category` trait
+// abstracting over them
+pub mod category {
+    pub use crate::categories::{
+        compose_all, Category, FnCat, KleisliCat, OptionKleisli, WriterKleisli,
+    };
+    pub use crate::ch1::{compose, id};
+    pub use crate::monoid_category::MonoidCat;
+    pub use crate::pipeline::{Pipeline, Trace, TraceStep};
+    pub use crate::{compose, pipe};
+}
+
+// pointwise checks of the category (and monoid homomorphism) laws over sample
+// inputs, and seeded, shrinking value generators to produce those inputs
+pub mod laws {
+    pub use crate::arbitrary::{for_all, samples, Arbitrary, Counterexample, Gen};
+    pub use crate::category_laws::{
+        check_associativity, check_category_laws, check_left_identity, check_right_identity, Law,
+        LawViolation,
+    };
+    pub use crate::free_monoid::check_monoid_homomorphism;
+}
+
+// functors, bifunctors and natural transformations between them
+pub mod functor {
+    pub use crate::ch10::safe_head;
+    pub use crate::ch7::{reader_functor, Functor, GatLessFunctor};
+    pub use crate::ch8::Bifunctor;
+}
+
+// inspectable (struct), opaque (closure), thread-safe and async memoizers; eviction
+// policies to bound the first one's cache and snapshots to persist it
+pub mod memo {
+    pub use crate::ch2::{CacheStats, Memoizer};
+    pub use crate::memoize_alt::memoize;
+    pub use crate::memoize_async::AsyncMemoizer;
+    pub use crate::memoize_eviction::{
+        Clock, EvictionPolicy, Lfu, Lru, ManualClock, SystemClock, Ttl, Unbounded,
+    };
+    pub use crate::memoize_fallible::{ErrorCaching, Fallible, FallibleMemoizer};
+    pub use crate::memoize_multi::{CallWithParts, KeyArg};
+    pub use crate::memoize_persist::{
+        decode_snapshot, encode_snapshot, Codec, DecodeError, SnapshotError, FORMAT_VERSION,
+    };
+    pub use crate::memoize_sync::SyncMemoizer;
+}
+
+// Kleisli categories for partial functions and logging (Writer, plain or w/ a
+// log of timed spans), and for any monad in general
+pub mod kleisli {
+    pub use crate::ch4::writer::Writer;
+    pub use crate::ch4::{optional, writer};
+    pub use crate::monad::{
+        arrow, kleisli_compose, KleisliArrow, Monad, OptionM, ResultM, State, StateM, VecM, WriterM,
+    };
+    pub use crate::span_writer::{Span, Spans, Traced, Tracer};
+}
+
+// semigroups and monoids, w/ newtypes picking between a type's monoids, and
+// (parallel) folds driven by them
+pub mod monoid {
+    pub use crate::ch3::{All, Any, First, Last, Max, Min, Monoid, Product, Semigroup, Sum};
+    pub use crate::free_monoid::FreeMonoid;
+    pub use crate::monoid_category::Endo;
+    pub use crate::monoid_fold::{
+        par_fold_map, par_fold_map_chunked, MonoidIterator, DEFAULT_MIN_CHUNK,
+    };
+    pub use ctfp_derive::{Monoid, Semigroup};
+}
+
+// benchmark harness behind the `bench!` macro
+pub mod bench {
+    pub use crate::benchmark::{run, run_with_state, BenchConfig, BenchReport, REPORT_ENV_VAR};
+}
+
+// types w/ finitely many values, the functions between them as tables, and
+// exhaustive equality and isomorphism checks for such functions
+pub mod finite {
+    pub use crate::finite_types::{
+        count_functions, ext_eq, find_difference, is_isomorphism, Finite, FnTable,
+    };
+    pub use crate::impl_finite;
+}
+
+// algebraic data types
+pub mod data {
+    pub use crate::ch5::Either;
+    pub use crate::ch6::Shape;
+}
+
+pub mod curry {
+    pub use crate::ch9::{curry, uncurry};
+    pub use crate::currying_alt::{IsoCurry, Tupled};
+}
+
+// `use ctfp::prelude::*;` brings the traits (and so their methods) into scope
+// along w/ the most commonly used types and functions
+pub mod prelude {
+    pub use crate::category::{compose, id, pipe, Category};
+    pub use crate::curry::IsoCurry;
+    pub use crate::data::Either;
+    pub use crate::functor::{Bifunctor, Functor, GatLessFunctor};
+    pub use crate::kleisli::Writer;
+    pub use crate::memo::Memoizer;
+    pub use crate::monoid::{Monoid, MonoidIterator, Semigroup};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[test]
+    fn test_prelude_exposes_public_api() {
+        let double_then_incr = compose(|x: u32| x * 2, |x: u32| x + 1);
+        assert_eq!(double_then_incr(20), 41);
+
+        let mut memoized = Memoizer::new(id::<u32>);
+        assert_eq!(memoized.call(42), 42);
+
+        let e: Either<u8, &str> = Either::Left(1);
+        assert_eq!(e.bimap(|x| x as u32 + 1, str::len), Either::Left(2u32));
+
+        assert_eq!(Some(41u8).fmap(|x| x + 1), Some(42));
+    }
+}
@@ -6,7 +6,9 @@
 use std::collections::HashMap;
 use std::hash::Hash;
 
-fn memoize<T: Clone + PartialEq + Eq + Hash, U: Clone>(f: impl Fn(T) -> U) -> impl FnMut(T) -> U {
+pub fn memoize<T: Clone + PartialEq + Eq + Hash, U: Clone>(
+    f: impl Fn(T) -> U,
+) -> impl FnMut(T) -> U {
     let mut map: HashMap<T, U> = HashMap::new();
     move |x: T| map.entry(x).or_insert_with_key(|x| f(x.clone())).clone()
 }
@@ -14,7 +16,8 @@ fn memoize<T: Clone + PartialEq + Eq + Hash, U: Clone>(f: impl Fn(T) -> U) -> im
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::{Duration, Instant};
+    use crate::benchmark::BenchConfig;
+    use std::hint::black_box;
 
     fn fib(n: u32) -> u32 {
         match n {
@@ -29,61 +32,32 @@ mod tests {
         assert_eq!(8, fib_memoized(6));
     }
 
-    // naive pseudo-benchmarks for ballpark figures in lieu of using either
-    // Unstable's test::Benchmark or Criterion.
-    // [should really be macro_rules!()'ed to reduce duplication]
-    // run w/ `cargo test -- --include-ignored --show-output`
-    #[ignore]
-    #[test]
-    fn bench_fib() {
-        let mut d: Duration = Default::default();
-
-        for _ in 0..1000 {
-            let start = Instant::now();
-            for i in 0..=30 {
-                fib(i);
-            }
-            d += start.elapsed();
+    // ballpark figures, see crate::benchmark
+    // run w/ `cargo test --release -- --ignored --show-output bench_`
+    crate::bench!(bench_fib, BenchConfig::default(), {
+        for i in 0..=30 {
+            black_box(fib(black_box(i)));
         }
+    });
 
-        println!("{:?}", d / 1000);
-    }
-
-    #[ignore]
-    #[test]
-    fn bench_fib_memoized_no_warmup() {
-        let mut fib_memoized = memoize(fib);
-        let mut d: Duration = Default::default();
-
-        for _ in 0..1000 {
-            let start = Instant::now();
+    // the first sample includes filling the cache
+    crate::bench!(
+        bench_fib_memoized_no_warmup,
+        BenchConfig::default().warmup(0),
+        memoize(fib) => |fib_memoized| {
             for i in 0..=30 {
-                fib_memoized(i);
+                black_box(fib_memoized(black_box(i)));
             }
-            d += start.elapsed();
         }
+    );
 
-        println!("{:?}", d / 1000);
-    }
-
-    #[ignore]
-    #[test]
-    fn bench_fib_memoized_warmed_up() {
-        let mut fib_memoized = memoize(fib);
-        let mut d: Duration = Default::default();
-
-        for i in 0..=30 {
-            fib_memoized(i);
-        }
-
-        for _ in 0..1000 {
-            let start = Instant::now();
+    crate::bench!(
+        bench_fib_memoized_warmed_up,
+        BenchConfig::default(),
+        memoize(fib) => |fib_memoized| {
             for i in 0..=30 {
-                fib_memoized(i);
+                black_box(fib_memoized(black_box(i)));
             }
-            d += start.elapsed();
         }
-
-        println!("{:?}", d / 1000);
-    }
+    );
 }
@@ -0,0 +1,310 @@
+// Memoizing async functions (`Fn(T) -> impl Future<Output = U>`) w/o tying
+// the crate to any particular executor: only std's `Future`/`Waker` are used.
+//
+// Concurrent requests for a key whose value is still being computed don't
+// start another computation: they park their waker on the key's slot and are
+// woken when the first request (the "leader") stores its result. If the
+// leader is dropped before finishing, the slot is cleared and the waiters
+// woken, and whichever of them polls first takes over as the new leader.
+
+#![allow(unused)]
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+enum Slot<U> {
+    Pending(Vec<Waker>),
+    Ready(U),
+}
+
+pub struct AsyncMemoizer<T, U, F> {
+    f: F,
+    slots: Mutex<HashMap<T, Slot<U>>>,
+}
+
+impl<T, U, F, Fut> AsyncMemoizer<T, U, F>
+where
+    T: Clone + Eq + Hash,
+    U: Clone,
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = U>,
+{
+    pub fn new(f: F) -> Self {
+        Self {
+            f,
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn call(&self, arg: T) -> U {
+        loop {
+            let lead = {
+                let mut slots = self.slots.lock().unwrap();
+                match slots.get(&arg) {
+                    Some(Slot::Ready(v)) => return v.clone(),
+                    Some(Slot::Pending(_)) => false,
+                    None => {
+                        slots.insert(arg.clone(), Slot::Pending(Vec::new()));
+                        true
+                    }
+                }
+            };
+
+            if lead {
+                let guard = LeaderGuard {
+                    memo: self,
+                    arg: &arg,
+                    done: false,
+                };
+                let v = (self.f)(arg.clone()).await;
+                guard.complete(v.clone());
+                return v;
+            }
+
+            // `None`: the leader gave up, go round again to take over
+            let waited = Wait {
+                memo: self,
+                arg: &arg,
+            };
+            if let Some(v) = waited.await {
+                return v;
+            }
+        }
+    }
+
+    // cached value, if computed already
+    pub fn get(&self, arg: &T) -> Option<U> {
+        match self.slots.lock().unwrap().get(arg) {
+            Some(Slot::Ready(v)) => Some(v.clone()),
+            _ => None,
+        }
+    }
+
+    // number of keys currently being computed
+    pub fn in_flight(&self) -> usize {
+        let slots = self.slots.lock().unwrap();
+        slots
+            .values()
+            .filter(|s| matches!(s, Slot::Pending(_)))
+            .count()
+    }
+}
+
+impl<T: Eq + Hash, U, F> AsyncMemoizer<T, U, F> {
+    // replace `arg`'s slot and wake whoever was waiting on it
+    fn settle(&self, arg: &T, new: Option<Slot<U>>) {
+        let mut slots = self.slots.lock().unwrap();
+        let old = match new {
+            Some(slot) => slots.get_mut(arg).map(|s| std::mem::replace(s, slot)),
+            None => slots.remove(arg),
+        };
+        drop(slots);
+        if let Some(Slot::Pending(wakers)) = old {
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+}
+
+// clears the slot if the leading computation is dropped half-way, so that
+// waiters don't hang forever
+struct LeaderGuard<'a, T: Eq + Hash, U, F> {
+    memo: &'a AsyncMemoizer<T, U, F>,
+    arg: &'a T,
+    done: bool,
+}
+
+impl<T: Eq + Hash, U, F> LeaderGuard<'_, T, U, F> {
+    fn complete(mut self, v: U) {
+        self.done = true;
+        self.memo.settle(self.arg, Some(Slot::Ready(v)));
+    }
+}
+
+impl<T: Eq + Hash, U, F> Drop for LeaderGuard<'_, T, U, F> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.memo.settle(self.arg, None);
+        }
+    }
+}
+
+// resolves w/ the leader's result, or `None` if the leader was dropped
+struct Wait<'a, T, U, F> {
+    memo: &'a AsyncMemoizer<T, U, F>,
+    arg: &'a T,
+}
+
+impl<T: Eq + Hash, U: Clone, F> Future for Wait<'_, T, U, F> {
+    type Output = Option<U>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<U>> {
+        let mut slots = self.memo.slots.lock().unwrap();
+        match slots.get_mut(self.arg) {
+            Some(Slot::Ready(v)) => Poll::Ready(Some(v.clone())),
+            None => Poll::Ready(None),
+            Some(Slot::Pending(wakers)) => {
+                if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                    wakers.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::task::Wake;
+    use std::thread::{self, Thread};
+    use std::time::Duration;
+
+    // minimal executor: block the current thread until the future is done,
+    // parking it in between polls
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let mut fut = std::pin::pin!(fut);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match fut.as_mut().poll(&mut cx) {
+                Poll::Ready(v) => return v,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    // poll all futures round-robin on this thread until they're all done,
+    // so they genuinely interleave
+    fn join_all<F: Future>(futs: Vec<F>) -> Vec<F::Output> {
+        let mut futs: Vec<_> = futs.into_iter().map(Box::pin).collect();
+        let mut outs: Vec<Option<F::Output>> = futs.iter().map(|_| None).collect();
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        while outs.iter().any(Option::is_none) {
+            for (fut, out) in futs.iter_mut().zip(outs.iter_mut()) {
+                if out.is_none() {
+                    if let Poll::Ready(v) = fut.as_mut().poll(&mut cx) {
+                        *out = Some(v);
+                    }
+                }
+            }
+        }
+        outs.into_iter().map(Option::unwrap).collect()
+    }
+
+    // returns Pending `n` times before completing
+    struct YieldNow(u32);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 == 0 {
+                return Poll::Ready(());
+            }
+            self.0 -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn test_async_memoizer_caches() {
+        let calls = Cell::new(0);
+        let memo = AsyncMemoizer::new(|n: u64| {
+            calls.set(calls.get() + 1);
+            async move { n * n }
+        });
+
+        assert_eq!(block_on(memo.call(7)), 49);
+        assert_eq!(block_on(memo.call(7)), 49);
+        assert_eq!(memo.get(&7), Some(49));
+        assert_eq!(memo.get(&8), None);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_requests_are_coalesced() {
+        let calls = Cell::new(0);
+        let memo = AsyncMemoizer::new(|n: u64| {
+            calls.set(calls.get() + 1);
+            async move {
+                YieldNow(5).await;
+                n + 1
+            }
+        });
+
+        let results = join_all((0..10).map(|i| memo.call(i % 2)).collect());
+        assert_eq!(results, [1, 2].repeat(5));
+        assert_eq!(calls.get(), 2);
+        assert_eq!(memo.in_flight(), 0);
+    }
+
+    #[test]
+    fn test_dropped_leader_hands_over() {
+        let calls = Cell::new(0);
+        let memo = AsyncMemoizer::new(|n: u64| {
+            calls.set(calls.get() + 1);
+            async move {
+                YieldNow(1).await;
+                n
+            }
+        });
+
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+
+        let mut leader = Box::pin(memo.call(1));
+        let mut follower = Box::pin(memo.call(1));
+        assert!(leader.as_mut().poll(&mut cx).is_pending());
+        assert!(follower.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(memo.in_flight(), 1);
+
+        drop(leader);
+        assert_eq!(memo.in_flight(), 0);
+        assert_eq!(block_on(follower), 1);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_coalesced_across_threads() {
+        const THREADS: usize = 8;
+
+        let calls = AtomicUsize::new(0);
+        let memo = AsyncMemoizer::new(|n: u64| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                // a blocking "computation" to keep the others waiting
+                thread::sleep(Duration::from_millis(20));
+                n * 3
+            }
+        });
+        let barrier = Barrier::new(THREADS);
+
+        thread::scope(|s| {
+            for _ in 0..THREADS {
+                s.spawn(|| {
+                    barrier.wait();
+                    assert_eq!(block_on(memo.call(14)), 42);
+                });
+            }
+        });
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,366 @@
+// Eviction policies bounding ch2's Memoizer cache: LRU and LFU w/ a fixed
+// capacity, and time-to-live w/ an injectable clock (so tests don't have to
+// sleep). The memoizer owns the actual values; policies only track keys and
+// tell it which ones to drop.
+
+#![allow(unused)]
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+pub trait EvictionPolicy<T> {
+    // `key` was found in the cache
+    fn on_hit(&mut self, key: &T) {}
+
+    // checked on lookup: an expired entry is evicted and recomputed
+    fn is_expired(&self, key: &T) -> bool {
+        false
+    }
+
+    // `key` was just added; returns the keys to evict to make room
+    fn on_insert(&mut self, key: &T) -> Vec<T>;
+
+    // `key` left the cache for any other reason (expiry, invalidation)
+    fn on_remove(&mut self, key: &T);
+
+    // the cache was emptied
+    fn clear(&mut self);
+}
+
+// never evicts: the default, and ch2's original behaviour
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Unbounded;
+
+impl<T> EvictionPolicy<T> for Unbounded {
+    fn on_insert(&mut self, _: &T) -> Vec<T> {
+        Vec::new()
+    }
+
+    fn on_remove(&mut self, _: &T) {}
+
+    fn clear(&mut self) {}
+}
+
+// least recently used; `tick` orders accesses, so `order`'s first entry is
+// always the LRU key
+#[derive(Debug)]
+pub struct Lru<T> {
+    capacity: usize,
+    tick: u64,
+    last_used: HashMap<T, u64>,
+    order: BTreeMap<u64, T>,
+}
+
+impl<T> Lru<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "LRU capacity must be non-zero");
+        Self {
+            capacity,
+            tick: 0,
+            last_used: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> Lru<T> {
+    fn touch(&mut self, key: &T) {
+        self.tick += 1;
+        if let Some(old) = self.last_used.insert(key.clone(), self.tick) {
+            self.order.remove(&old);
+        }
+        self.order.insert(self.tick, key.clone());
+    }
+}
+
+impl<T: Clone + Eq + Hash> EvictionPolicy<T> for Lru<T> {
+    fn on_hit(&mut self, key: &T) {
+        self.touch(key);
+    }
+
+    fn on_insert(&mut self, key: &T) -> Vec<T> {
+        self.touch(key);
+        let mut victims = Vec::new();
+        while self.last_used.len() > self.capacity {
+            let (_, victim) = self.order.pop_first().expect("LRU order out of sync");
+            self.last_used.remove(&victim);
+            victims.push(victim);
+        }
+        victims
+    }
+
+    fn on_remove(&mut self, key: &T) {
+        if let Some(t) = self.last_used.remove(key) {
+            self.order.remove(&t);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.last_used.clear();
+        self.order.clear();
+    }
+}
+
+// least frequently used, ties broken by least recent use
+#[derive(Debug)]
+pub struct Lfu<T> {
+    capacity: usize,
+    tick: u64,
+    // key -> (use count, tick of last use)
+    uses: HashMap<T, (u64, u64)>,
+    order: BTreeMap<(u64, u64), T>,
+}
+
+impl<T> Lfu<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "LFU capacity must be non-zero");
+        Self {
+            capacity,
+            tick: 0,
+            uses: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> Lfu<T> {
+    fn touch(&mut self, key: &T) {
+        self.tick += 1;
+        let count = match self.uses.get(key) {
+            Some(&old) => {
+                self.order.remove(&old);
+                old.0 + 1
+            }
+            None => 1,
+        };
+        self.uses.insert(key.clone(), (count, self.tick));
+        self.order.insert((count, self.tick), key.clone());
+    }
+}
+
+impl<T: Clone + Eq + Hash> EvictionPolicy<T> for Lfu<T> {
+    fn on_hit(&mut self, key: &T) {
+        self.touch(key);
+    }
+
+    // the new key itself is never the victim, else nothing new would ever
+    // make it into a full cache
+    fn on_insert(&mut self, key: &T) -> Vec<T> {
+        let mut victims = Vec::new();
+        if !self.uses.contains_key(key) {
+            while self.uses.len() >= self.capacity {
+                let (_, victim) = self.order.pop_first().expect("LFU order out of sync");
+                self.uses.remove(&victim);
+                victims.push(victim);
+            }
+        }
+        self.touch(key);
+        victims
+    }
+
+    fn on_remove(&mut self, key: &T) {
+        if let Some(u) = self.uses.remove(key) {
+            self.order.remove(&u);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.uses.clear();
+        self.order.clear();
+    }
+}
+
+// time source for the time-based policies: time elapsed since some fixed
+// (clock-specific) origin
+pub trait Clock {
+    fn now(&self) -> Duration;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+// a clock that only moves when told to; clones share the same time, so a
+// test can keep one handle and give the other to the memoizer
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+
+    pub fn set(&self, to: Duration) {
+        self.now.set(to);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+// time to live: entries expire `ttl` after being inserted (hits don't
+// extend their life)
+pub struct Ttl<T> {
+    ttl: Duration,
+    clock: Box<dyn Clock>,
+    deadlines: HashMap<T, (Duration, u64)>,
+    // ordered by deadline (ties broken by insertion), not by insertion: a
+    // clock may be set back, so a later insert can expire sooner
+    queue: BTreeMap<(Duration, u64), T>,
+    tick: u64,
+}
+
+impl<T> Ttl<T> {
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_clock(ttl, SystemClock::new())
+    }
+
+    pub fn with_clock(ttl: Duration, clock: impl Clock + 'static) -> Self {
+        Self {
+            ttl,
+            clock: Box::new(clock),
+            deadlines: HashMap::new(),
+            queue: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> EvictionPolicy<T> for Ttl<T> {
+    fn is_expired(&self, key: &T) -> bool {
+        self.deadlines
+            .get(key)
+            .is_some_and(|&(deadline, _)| deadline <= self.clock.now())
+    }
+
+    // also sweeps out everything that expired in the meantime, so entries
+    // that are never looked up again don't linger forever
+    fn on_insert(&mut self, key: &T) -> Vec<T> {
+        let now = self.clock.now();
+        self.on_remove(key);
+        let mut victims = Vec::new();
+        while let Some(entry) = self.queue.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            let victim = entry.remove();
+            self.deadlines.remove(&victim);
+            victims.push(victim);
+        }
+        self.tick += 1;
+        let slot = (now + self.ttl, self.tick);
+        self.deadlines.insert(key.clone(), slot);
+        self.queue.insert(slot, key.clone());
+        victims
+    }
+
+    fn on_remove(&mut self, key: &T) {
+        if let Some(slot) = self.deadlines.remove(key) {
+            self.queue.remove(&slot);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.deadlines.clear();
+        self.queue.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_evicts_least_recently_used() {
+        let mut lru = Lru::new(2);
+        assert!(lru.on_insert(&1).is_empty());
+        assert!(lru.on_insert(&2).is_empty());
+        lru.on_hit(&1);
+        assert_eq!(lru.on_insert(&3), vec![2]);
+        assert_eq!(lru.on_insert(&4), vec![1]);
+    }
+
+    #[test]
+    fn test_lfu_evicts_least_frequently_used() {
+        let mut lfu = Lfu::new(2);
+        lfu.on_insert(&1);
+        lfu.on_insert(&2);
+        lfu.on_hit(&1);
+        lfu.on_hit(&1);
+        lfu.on_hit(&2);
+        assert_eq!(lfu.on_insert(&3), vec![2]);
+        // 3 is the least frequently used now, even though it's the newest
+        assert_eq!(lfu.on_insert(&4), vec![3]);
+    }
+
+    #[test]
+    fn test_ttl_expiry_and_sweep() {
+        let clock = ManualClock::new();
+        let mut ttl = Ttl::with_clock(Duration::from_secs(10), clock.clone());
+        ttl.on_insert(&"a");
+        clock.advance(Duration::from_secs(5));
+        ttl.on_insert(&"b");
+        assert!(!ttl.is_expired(&"a"));
+
+        clock.advance(Duration::from_secs(5));
+        assert!(ttl.is_expired(&"a"));
+        assert!(!ttl.is_expired(&"b"));
+
+        // "a" gets swept on the next insert, "b" is still live
+        assert_eq!(ttl.on_insert(&"c"), vec!["a"]);
+        clock.advance(Duration::from_secs(20));
+        assert_eq!(ttl.on_insert(&"c"), vec!["b"]);
+    }
+
+    #[test]
+    fn test_ttl_with_clock_set_back() {
+        let clock = ManualClock::new();
+        let mut ttl = Ttl::with_clock(Duration::from_secs(10), clock.clone());
+        clock.set(Duration::from_secs(100));
+        ttl.on_insert(&"late");
+        // inserted after "late" but expiring before it
+        clock.set(Duration::from_secs(0));
+        ttl.on_insert(&"early");
+
+        clock.set(Duration::from_secs(10));
+        assert!(ttl.is_expired(&"early"));
+        assert!(!ttl.is_expired(&"late"));
+        assert_eq!(ttl.on_insert(&"x"), vec!["early"]);
+
+        clock.set(Duration::from_secs(110));
+        assert_eq!(ttl.on_insert(&"y"), vec!["x", "late"]);
+    }
+}
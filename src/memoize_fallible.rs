@@ -0,0 +1,208 @@
+// Memoizing fallible functions, i.e. ones returning `Result` or (ch4-style
+// partial functions) `Option`. A plain Memoizer caches failures forever,
+// which is wrong for transient ones; here the caller picks whether failures
+// are cached never, always, or only for a while.
+
+#![allow(unused)]
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+use crate::ch2::{CacheStats, Memoizer};
+use crate::memoize_eviction::{Clock, SystemClock};
+
+// results that can be failures: `Err(_)` and `None`
+pub trait Fallible {
+    fn is_failure(&self) -> bool;
+}
+
+impl<V, E> Fallible for Result<V, E> {
+    fn is_failure(&self) -> bool {
+        self.is_err()
+    }
+}
+
+impl<V> Fallible for Option<V> {
+    fn is_failure(&self) -> bool {
+        self.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCaching {
+    // only successes are cached; failures are retried on every call
+    SuccessesOnly,
+    // failures are cached for the given time, successes for good
+    FailuresFor(Duration),
+    // cache everything, like a plain Memoizer
+    Both,
+}
+
+pub struct FallibleMemoizer<T, R> {
+    memo: Memoizer<T, R>,
+    caching: ErrorCaching,
+    clock: Box<dyn Clock>,
+    // when each cached failure expires (FailuresFor only)
+    failure_deadlines: HashMap<T, Duration>,
+}
+
+impl<T: Clone + Eq + Hash, R: Clone + Fallible + 'static> FallibleMemoizer<T, R> {
+    pub fn new(f: impl Fn(T) -> R + 'static, caching: ErrorCaching) -> Self {
+        Self::with_clock(f, caching, SystemClock::new())
+    }
+
+    pub fn with_clock(
+        f: impl Fn(T) -> R + 'static,
+        caching: ErrorCaching,
+        clock: impl Clock + 'static,
+    ) -> Self {
+        let memo = match caching {
+            ErrorCaching::SuccessesOnly => Memoizer::new(f).cache_if(|r: &R| !r.is_failure()),
+            _ => Memoizer::new(f),
+        };
+        Self {
+            memo,
+            caching,
+            clock: Box::new(clock),
+            failure_deadlines: HashMap::new(),
+        }
+    }
+
+    pub fn call(&mut self, arg: T) -> R {
+        if let Some(&deadline) = self.failure_deadlines.get(&arg) {
+            if deadline <= self.clock.now() {
+                self.failure_deadlines.remove(&arg);
+                self.memo.invalidate(&arg);
+            }
+        }
+
+        let r = self.memo.call(arg.clone());
+        if let ErrorCaching::FailuresFor(ttl) = self.caching {
+            // only a freshly computed failure starts the clock
+            if r.is_failure() && !self.failure_deadlines.contains_key(&arg) {
+                self.failure_deadlines.insert(arg, self.clock.now() + ttl);
+            }
+        }
+        r
+    }
+
+    // the underlying memoizer, for stats and cache inspection
+    pub fn memoizer(&self) -> &Memoizer<T, R> {
+        &self.memo
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.memo.stats()
+    }
+
+    pub fn invalidate(&mut self, arg: &T) -> Option<R> {
+        self.failure_deadlines.remove(arg);
+        self.memo.invalidate(arg)
+    }
+
+    pub fn clear(&mut self) {
+        self.failure_deadlines.clear();
+        self.memo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memoize_eviction::ManualClock;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    // fails for the first `failures` calls, then succeeds
+    fn flaky(failures: u32) -> (impl Fn(u32) -> Result<u32, String>, Rc<Cell<u32>>) {
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        let f = move |n: u32| {
+            counter.set(counter.get() + 1);
+            if counter.get() <= failures {
+                Err(format!("transient failure #{}", counter.get()))
+            } else {
+                Ok(n * 2)
+            }
+        };
+        (f, calls)
+    }
+
+    #[test]
+    fn test_successes_only() {
+        let (f, calls) = flaky(2);
+        let mut memoized = FallibleMemoizer::new(f, ErrorCaching::SuccessesOnly);
+
+        assert!(memoized.call(21).is_err());
+        assert!(memoized.call(21).is_err());
+        assert_eq!(memoized.call(21), Ok(42));
+        assert_eq!(memoized.call(21), Ok(42));
+        assert_eq!(calls.get(), 3);
+        assert_eq!(memoized.stats().size, 1);
+    }
+
+    #[test]
+    fn test_both() {
+        let (f, calls) = flaky(1);
+        let mut memoized = FallibleMemoizer::new(f, ErrorCaching::Both);
+
+        let first = memoized.call(21);
+        assert_eq!(first, Err("transient failure #1".to_string()));
+        assert_eq!(memoized.call(21), first);
+        assert_eq!(calls.get(), 1);
+
+        memoized.invalidate(&21);
+        assert_eq!(memoized.call(21), Ok(42));
+    }
+
+    #[test]
+    fn test_failures_expire() {
+        let clock = ManualClock::new();
+        let (f, calls) = flaky(2);
+        let mut memoized = FallibleMemoizer::with_clock(
+            f,
+            ErrorCaching::FailuresFor(Duration::from_secs(30)),
+            clock.clone(),
+        );
+
+        assert!(memoized.call(21).is_err());
+        clock.advance(Duration::from_secs(20));
+        // still the cached failure, whose clock didn't restart on the hit
+        assert_eq!(memoized.call(21), Err("transient failure #1".to_string()));
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(memoized.call(21), Err("transient failure #2".to_string()));
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(memoized.call(21), Ok(42));
+
+        // successes don't expire
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(memoized.call(21), Ok(42));
+        assert_eq!(calls.get(), 3);
+    }
+
+    // ch4's partial functions, but on integers so they can be cache keys
+    fn safe_int_root(n: i64) -> Option<i64> {
+        (n >= 0).then(|| (n as f64).sqrt() as i64)
+    }
+
+    #[test]
+    fn test_option_returning_partial_function() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&seen);
+        let mut memoized = FallibleMemoizer::new(
+            move |n| {
+                log.borrow_mut().push(n);
+                safe_int_root(n)
+            },
+            ErrorCaching::SuccessesOnly,
+        );
+
+        assert_eq!(memoized.call(16), Some(4));
+        assert_eq!(memoized.call(16), Some(4));
+        assert_eq!(memoized.call(-16), None);
+        assert_eq!(memoized.call(-16), None);
+        assert_eq!(*seen.borrow(), vec![16, -16, -16]);
+        assert_eq!(memoized.memoizer().peek(&-16), None);
+    }
+}
@@ -0,0 +1,242 @@
+// Memoizing functions of borrowed arguments, like ch9's
+// `count_char(&str, char)`, on owned tuple keys, e.g. `(String, char)`.
+//
+// The cache can't be queried by `(&str, char)` directly: a tuple of owned
+// parts doesn't `Borrow` as the tuple of their borrowed forms. Instead both
+// the stored key and the query are looked at through a `dyn KeyView2` (and
+// so on up to 6 parts), which hashes and compares the parts' borrowed forms.
+// As `Borrow` requires, those hash and compare like the owned parts, so a
+// query finds the owned key w/o allocating one; that only happens on a miss.
+//
+//   let mut counts: Memoizer<(String, char), usize> = Memoizer::new_multi_borrowed(count_char);
+//   counts.call2("element", 'e');
+
+#![allow(unused)]
+
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+
+use crate::ch2::Memoizer;
+
+// an owned key part, w/ the argument form the memoized function takes it in
+// (`&str` for `String`) and the borrowed form it's looked up by (`str`)
+pub trait KeyArg: Clone + Eq + Hash + Borrow<Self::View> {
+    type View: Eq + Hash + ?Sized;
+    type Arg<'a>: Copy
+    where
+        Self: 'a;
+
+    fn arg(&self) -> Self::Arg<'_>;
+    fn view<'a>(arg: &'a Self::Arg<'_>) -> &'a Self::View;
+    fn from_arg(arg: Self::Arg<'_>) -> Self;
+}
+
+impl KeyArg for String {
+    type View = str;
+    type Arg<'a> = &'a str;
+
+    fn arg(&self) -> &str {
+        self
+    }
+
+    fn view<'a>(arg: &'a &str) -> &'a str {
+        arg
+    }
+
+    fn from_arg(arg: &str) -> Self {
+        arg.to_owned()
+    }
+}
+
+impl<T: Clone + Eq + Hash> KeyArg for Vec<T> {
+    type View = [T];
+    type Arg<'a>
+        = &'a [T]
+    where
+        T: 'a;
+
+    fn arg(&self) -> &[T] {
+        self
+    }
+
+    fn view<'a>(arg: &'a &[T]) -> &'a [T] {
+        arg
+    }
+
+    fn from_arg(arg: &[T]) -> Self {
+        arg.to_vec()
+    }
+}
+
+// passed and looked up by value
+macro_rules! key_arg_by_value {
+    ($($t:ty),*) => {
+        $(
+            impl KeyArg for $t {
+                type View = $t;
+                type Arg<'a> = $t;
+
+                fn arg(&self) -> $t {
+                    *self
+                }
+
+                fn view(arg: &$t) -> &$t {
+                    arg
+                }
+
+                fn from_arg(arg: $t) -> Self {
+                    arg
+                }
+            }
+        )*
+    };
+}
+
+key_arg_by_value!(bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+// a function of the parts of a tuple key, taking each in its argument form;
+// the borrowed counterpart of currying_alt's `Tupled`
+pub trait CallWithParts<K, U> {
+    fn call_with_parts(&self, key: &K) -> U;
+}
+
+impl<T: Clone + Eq + Hash, U: Clone> Memoizer<T, U> {
+    // calls `f` w/ the key's parts in their argument forms, e.g. `&str` for
+    // `String`; look them up by those forms too w/ `call2`, `call3`...
+    pub fn new_multi_borrowed(f: impl CallWithParts<T, U> + 'static) -> Self {
+        Self::new(move |key: T| f.call_with_parts(&key))
+    }
+}
+
+macro_rules! multi_key {
+    ($(($view:ident, $query:ident, $call:ident; $($t:ident $a:ident $i:tt),+)),*) => {
+        $(
+            // the parts of a key, borrowed
+            pub trait $view<$($t: KeyArg),+> {
+                fn parts(&self) -> ($(&$t::View,)+);
+            }
+
+            impl<$($t: KeyArg),+> $view<$($t),+> for ($($t,)+) {
+                fn parts(&self) -> ($(&$t::View,)+) {
+                    ($(self.$i.borrow(),)+)
+                }
+            }
+
+            // hashes like the owned tuple would: part by part
+            impl<$($t: KeyArg),+> Hash for dyn $view<$($t),+> + '_ {
+                fn hash<H: Hasher>(&self, state: &mut H) {
+                    $(self.parts().$i.hash(state);)+
+                }
+            }
+
+            impl<$($t: KeyArg),+> PartialEq for dyn $view<$($t),+> + '_ {
+                fn eq(&self, other: &Self) -> bool {
+                    self.parts() == other.parts()
+                }
+            }
+
+            impl<$($t: KeyArg),+> Eq for dyn $view<$($t),+> + '_ {}
+
+            impl<'k, $($t: KeyArg + 'k),+> Borrow<dyn $view<$($t),+> + 'k> for ($($t,)+) {
+                fn borrow(&self) -> &(dyn $view<$($t),+> + 'k) {
+                    self
+                }
+            }
+
+            struct $query<'q, $($t: KeyArg),+>($(&'q $t::View),+);
+
+            impl<$($t: KeyArg),+> $view<$($t),+> for $query<'_, $($t),+> {
+                fn parts(&self) -> ($(&$t::View,)+) {
+                    ($(self.$i,)+)
+                }
+            }
+
+            impl<$($t: KeyArg),+ , U, F> CallWithParts<($($t,)+), U> for F
+            where
+                F: for<'a> Fn($($t::Arg<'a>),+) -> U,
+            {
+                fn call_with_parts(&self, key: &($($t,)+)) -> U {
+                    self($(key.$i.arg()),+)
+                }
+            }
+
+            impl<$($t: KeyArg + 'static),+ , U: Clone> Memoizer<($($t,)+), U> {
+                // `call` w/ the arguments as they'd be passed to the function;
+                // they're only turned into an owned key on a miss
+                pub fn $call(&mut self, $($a: $t::Arg<'_>),+) -> U {
+                    let query = $query::<$($t),+>($($t::view(&$a)),+);
+                    self.call_by(&query as &dyn $view<$($t),+>, || ($($t::from_arg($a),)+))
+                }
+            }
+        )*
+    };
+}
+
+multi_key!(
+    (KeyView2, Query2, call2; A a 0, B b 1),
+    (KeyView3, Query3, call3; A a 0, B b 1, C c 2),
+    (KeyView4, Query4, call4; A a 0, B b 1, C c 2, D d 3),
+    (KeyView5, Query5, call5; A a 0, B b 1, C c 2, D d 3, E e 4),
+    (KeyView6, Query6, call6; A a 0, B b 1, C c 2, D d 3, E e 4, G g 5)
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currying_alt::count_char;
+    use crate::memoize_eviction::Lru;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_borrowed_lookups_share_owned_entries() {
+        let mut counts: Memoizer<(String, char), usize> = Memoizer::new_multi_borrowed(count_char);
+        assert_eq!(counts.call2("element", 'e'), 3);
+        assert_eq!(counts.call(("element".to_string(), 'e')), 3);
+        assert_eq!(counts.call2("element", 'e'), 3);
+        assert_eq!(counts.call2("element", 'l'), 1);
+
+        let stats = counts.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 2));
+        assert_eq!(counts.peek(&("element".to_string(), 'l')), Some(&1));
+    }
+
+    type SpliceArgs = (Vec<u8>, usize, usize, Vec<u8>, bool, char);
+
+    #[test]
+    fn test_borrowed_lookups_up_to_six_parts() {
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        let mut spliced: Memoizer<SpliceArgs, Vec<u8>> = Memoizer::new_multi_borrowed(
+            move |xs: &[u8], at: usize, len: usize, with: &[u8], rev: bool, _tag: char| {
+                counter.set(counter.get() + 1);
+                let mut out = xs.to_vec();
+                out.splice(at..at + len, with.iter().copied());
+                if rev {
+                    out.reverse();
+                }
+                out
+            },
+        );
+        let xs = [1, 2, 3, 4];
+        assert_eq!(spliced.call6(&xs, 1, 2, &[9], false, 'x'), [1, 9, 4]);
+        assert_eq!(spliced.call6(&xs, 1, 2, &[9], false, 'x'), [1, 9, 4]);
+        assert_eq!(spliced.call6(&xs, 1, 2, &[9], true, 'x'), [4, 9, 1]);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_borrowed_lookups_respect_policy() {
+        let mut counts: Memoizer<(String, char), usize> =
+            Memoizer::new_multi_borrowed(count_char).with_policy(Lru::new(2));
+        counts.call2("a", 'a');
+        counts.call2("bb", 'b');
+        // a hit through the borrowed form still counts as a use for the LRU
+        counts.call2("a", 'a');
+        counts.call2("ccc", 'c');
+
+        assert_eq!(counts.peek(&("a".to_string(), 'a')), Some(&1));
+        assert_eq!(counts.peek(&("bb".to_string(), 'b')), None);
+        assert_eq!(counts.evictions(), 1);
+    }
+}
@@ -0,0 +1,651 @@
+// Saving/loading ch2's Memoizer cache to/from disk, so that expensive results
+// survive restarts.
+//
+// Snapshot format (all integers little-endian):
+//   magic     8 bytes  b"CTFPMEMO"
+//   version   u16      FORMAT_VERSION
+//   count     u64      number of entries
+//   entries            `count` x (key, value), each w/ its `Codec` encoding
+//   checksum  u64      FNV-1a over everything above
+// Anything that doesn't match is reported as a `SnapshotError`, never a panic.
+
+#![allow(unused)]
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::path::Path;
+
+use crate::ch2::Memoizer;
+
+pub const MAGIC: &[u8; 8] = b"CTFPMEMO";
+pub const FORMAT_VERSION: u16 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    UnexpectedEof,
+    Invalid(&'static str),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEof => f.write_str("unexpected end of input"),
+            DecodeError::Invalid(what) => write!(f, "invalid {}", what),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    // not a snapshot at all
+    BadMagic,
+    UnsupportedVersion { found: u16, expected: u16 },
+    // too short to even hold the header and checksum
+    Truncated,
+    ChecksumMismatch,
+    // checksum is fine, but the entries don't decode as the expected types
+    Corrupt(DecodeError),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "snapshot I/O error: {}", e),
+            SnapshotError::BadMagic => f.write_str("not a memoizer snapshot"),
+            SnapshotError::UnsupportedVersion { found, expected } => write!(
+                f,
+                "unsupported snapshot version {} (expected {})",
+                found, expected
+            ),
+            SnapshotError::Truncated => f.write_str("truncated snapshot"),
+            SnapshotError::ChecksumMismatch => f.write_str("snapshot checksum mismatch"),
+            SnapshotError::Corrupt(e) => write!(f, "corrupt snapshot: {}", e),
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(e) => Some(e),
+            SnapshotError::Corrupt(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+impl From<DecodeError> for SnapshotError {
+    fn from(e: DecodeError) -> Self {
+        SnapshotError::Corrupt(e)
+    }
+}
+
+// binary encoding for snapshot keys and values; `decode` consumes its bytes
+// from the front of `input`
+pub trait Codec: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+fn take<'a>(input: &mut &'a [u8], n: usize) -> Result<&'a [u8], DecodeError> {
+    if input.len() < n {
+        return Err(DecodeError::UnexpectedEof);
+    }
+    let (head, tail) = input.split_at(n);
+    *input = tail;
+    Ok(head)
+}
+
+fn take_array<const N: usize>(input: &mut &[u8]) -> Result<[u8; N], DecodeError> {
+    Ok(take(input, N)?.try_into().unwrap())
+}
+
+macro_rules! codec_number {
+    ($($t:ty),*) => {
+        $(
+            impl Codec for $t {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                    Ok(<$t>::from_le_bytes(take_array(input)?))
+                }
+            }
+        )*
+    };
+}
+
+codec_number!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+// pointer-sized ints are stored as 64 bits, so snapshots are portable
+impl Codec for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out)
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        usize::try_from(u64::decode(input)?).map_err(|_| DecodeError::Invalid("usize"))
+    }
+}
+
+impl Codec for isize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as i64).encode(out)
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        isize::try_from(i64::decode(input)?).map_err(|_| DecodeError::Invalid("isize"))
+    }
+}
+
+impl Codec for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8)
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::Invalid("bool")),
+        }
+    }
+}
+
+impl Codec for char {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u32).encode(out)
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        char::from_u32(u32::decode(input)?).ok_or(DecodeError::Invalid("char"))
+    }
+}
+
+impl Codec for () {
+    fn encode(&self, _: &mut Vec<u8>) {}
+
+    fn decode(_: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(())
+    }
+}
+
+fn decode_len(input: &mut &[u8]) -> Result<usize, DecodeError> {
+    usize::try_from(u64::decode(input)?).map_err(|_| DecodeError::Invalid("length"))
+}
+
+impl Codec for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u64).encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let n = decode_len(input)?;
+        let bytes = take(input, n)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::Invalid("UTF-8 string"))
+    }
+}
+
+impl<T: Codec> Codec for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u64).encode(out);
+        for x in self {
+            x.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let n = decode_len(input)?;
+        // don't trust `n` for the allocation: every element takes up at
+        // least a byte, except zero-sized ones. Those never run out of input
+        // to stop a bogus `n`, so their count is bounded by what's left of it
+        // too, same as the snapshot's entry count
+        if mem::size_of::<T>() == 0 && n > input.len().max(1) {
+            return Err(DecodeError::Invalid("length"));
+        }
+        let mut xs = Vec::with_capacity(n.min(input.len()));
+        for _ in 0..n {
+            xs.push(T::decode(input)?);
+        }
+        Ok(xs)
+    }
+}
+
+impl<T: Codec> Codec for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(x) => {
+                out.push(1);
+                x.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(input)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(input)?)),
+            _ => Err(DecodeError::Invalid("Option tag")),
+        }
+    }
+}
+
+impl<T: Codec, E: Codec> Codec for Result<T, E> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Ok(x) => {
+                out.push(0);
+                x.encode(out);
+            }
+            Err(e) => {
+                out.push(1);
+                e.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(input)? {
+            0 => Ok(Ok(T::decode(input)?)),
+            1 => Ok(Err(E::decode(input)?)),
+            _ => Err(DecodeError::Invalid("Result tag")),
+        }
+    }
+}
+
+macro_rules! codec_tuple {
+    ($(($($t:ident $i:tt),+)),*) => {
+        $(
+            impl<$($t: Codec),+> Codec for ($($t,)+) {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    $(self.$i.encode(out);)+
+                }
+
+                fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                    Ok(($($t::decode(input)?,)+))
+                }
+            }
+        )*
+    };
+}
+
+codec_tuple!(
+    (A 0),
+    (A 0, B 1),
+    (A 0, B 1, C 2),
+    (A 0, B 1, C 2, D 3),
+    (A 0, B 1, C 2, D 3, E 4),
+    (A 0, B 1, C 2, D 3, E 4, F 5)
+);
+
+// FNV-1a, 64 bit
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| {
+        (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+const HEADER_LEN: usize = MAGIC.len() + 2 + 8;
+const CHECKSUM_LEN: usize = 8;
+
+pub fn encode_snapshot<'a, T: Codec + 'a, U: Codec + 'a>(
+    entries: impl ExactSizeIterator<Item = (&'a T, &'a U)>,
+) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    FORMAT_VERSION.encode(&mut out);
+    (entries.len() as u64).encode(&mut out);
+    for (k, v) in entries {
+        k.encode(&mut out);
+        v.encode(&mut out);
+    }
+    checksum(&out).encode(&mut out);
+    out
+}
+
+pub fn decode_snapshot<T: Codec, U: Codec>(bytes: &[u8]) -> Result<Vec<(T, U)>, SnapshotError> {
+    // check what we can as early as possible, so that e.g. a file from a
+    // newer version is reported as such rather than as a bad checksum
+    if bytes.len() < MAGIC.len() {
+        return Err(SnapshotError::Truncated);
+    }
+    if &bytes[..MAGIC.len()] != MAGIC {
+        return Err(SnapshotError::BadMagic);
+    }
+    if bytes.len() < HEADER_LEN + CHECKSUM_LEN {
+        return Err(SnapshotError::Truncated);
+    }
+    let mut input = &bytes[MAGIC.len()..];
+    let version = u16::decode(&mut input)?;
+    if version != FORMAT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion {
+            found: version,
+            expected: FORMAT_VERSION,
+        });
+    }
+
+    let (body, mut sum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    if checksum(body) != u64::decode(&mut sum)? {
+        return Err(SnapshotError::ChecksumMismatch);
+    }
+
+    let count = u64::decode(&mut input)?;
+    let mut input = &body[HEADER_LEN..];
+    // don't trust `count` either: every entry takes up at least a byte,
+    // except zero-sized ones, and as keys are unique there's at most one of
+    // those
+    if count > input.len().max(1) as u64 {
+        return Err(SnapshotError::Corrupt(DecodeError::Invalid("entry count")));
+    }
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let k = T::decode(&mut input)?;
+        let v = U::decode(&mut input)?;
+        entries.push((k, v));
+    }
+    if !input.is_empty() {
+        return Err(SnapshotError::Corrupt(DecodeError::Invalid(
+            "trailing bytes after last entry",
+        )));
+    }
+    Ok(entries)
+}
+
+impl<T, U> Memoizer<T, U>
+where
+    T: Clone + Eq + Hash + Codec,
+    U: Clone + Codec,
+{
+    pub fn save_to(&self, mut w: impl Write) -> Result<(), SnapshotError> {
+        let entries: Vec<(&T, &U)> = self.iter().collect();
+        w.write_all(&encode_snapshot(entries.into_iter()))?;
+        w.flush()?;
+        Ok(())
+    }
+
+    // adds the snapshot's entries to the cache (going through the eviction
+    // policy); nothing is added if the snapshot turns out to be invalid
+    pub fn load_from(&mut self, mut r: impl Read) -> Result<usize, SnapshotError> {
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes)?;
+        let entries = decode_snapshot::<T, U>(&bytes)?;
+        let n = entries.len();
+        for (k, v) in entries {
+            self.insert(k, v);
+        }
+        Ok(n)
+    }
+
+    // written to a temporary file first, synced to disk, then renamed over
+    // `path`, so a crash mid-save can't leave a half-written snapshot behind.
+    // The temporary file is removed if any of that fails
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let written = fs::File::create(&tmp)
+            .map_err(SnapshotError::from)
+            .and_then(|mut file| {
+                self.save_to(&mut file)?;
+                file.sync_all()?;
+                fs::rename(&tmp, path)?;
+                Ok(())
+            });
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        written?;
+        // the rename itself only survives a crash once the directory is synced
+        sync_parent_dir(path)?;
+        Ok(())
+    }
+
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<usize, SnapshotError> {
+        self.load_from(fs::File::open(path)?)
+    }
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::File::open(dir)?.sync_all()
+}
+
+// directories can't be opened (and so synced) like files elsewhere
+#[cfg(not(unix))]
+fn sync_parent_dir(_: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fib(n: u32) -> u32 {
+        match n {
+            0 | 1 => n,
+            _ => fib(n - 1) + fib(n - 2),
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ctfp-{}-{}.memo", name, std::process::id()))
+    }
+
+    fn roundtrip<T: Codec + PartialEq + fmt::Debug>(x: T) {
+        let mut out = Vec::new();
+        x.encode(&mut out);
+        let mut input = &out[..];
+        assert_eq!(T::decode(&mut input), Ok(x));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn test_codec_roundtrips() {
+        roundtrip(42u8);
+        roundtrip(-42i64);
+        roundtrip(usize::MAX);
+        roundtrip(1.5f64);
+        roundtrip('λ');
+        roundtrip(true);
+        roundtrip("hello, 世界".to_string());
+        roundtrip(vec![Some(1u16), None, Some(3)]);
+        roundtrip((1u8, "two".to_string(), vec![3.0f32]));
+        roundtrip::<Result<u32, String>>(Err("nope".to_string()));
+        roundtrip(());
+    }
+
+    #[test]
+    fn test_codec_rejects_invalid_input() {
+        assert_eq!(
+            bool::decode(&mut &[2u8][..]),
+            Err(DecodeError::Invalid("bool"))
+        );
+        assert_eq!(
+            u32::decode(&mut &[1u8, 2][..]),
+            Err(DecodeError::UnexpectedEof)
+        );
+        assert_eq!(
+            char::decode(&mut &0xd800u32.to_le_bytes()[..]),
+            Err(DecodeError::Invalid("char"))
+        );
+        // claims to hold way more elements than there are bytes
+        let mut huge = Vec::new();
+        u64::MAX.encode(&mut huge);
+        assert!(Vec::<u8>::decode(&mut &huge[..]).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load_file() {
+        let path = temp_path("roundtrip");
+        let mut fib_memoized = Memoizer::new(fib);
+        for i in 0..=10 {
+            fib_memoized.call(i);
+        }
+        fib_memoized.save(&path).unwrap();
+
+        let mut reloaded = Memoizer::new(fib);
+        assert_eq!(reloaded.load(&path).unwrap(), 11);
+        fs::remove_file(&path).unwrap();
+
+        let mut entries: Vec<_> = reloaded.iter().map(|(&k, &v)| (k, v)).collect();
+        entries.sort();
+        assert_eq!(entries, (0..=10).map(|i| (i, fib(i))).collect::<Vec<_>>());
+
+        // served from the loaded cache
+        reloaded.reset_stats();
+        reloaded.call(10);
+        assert_eq!(reloaded.stats().hits, 1);
+    }
+
+    #[test]
+    fn test_failed_save_leaves_no_temporary_file() {
+        // renaming a file over a non-empty directory fails
+        let path = temp_path("save-over-dir");
+        fs::create_dir_all(path.join("occupied")).unwrap();
+        let mut memoized = Memoizer::new(fib);
+        memoized.call(5);
+
+        let err = memoized.save(&path).unwrap_err();
+        assert!(matches!(err, SnapshotError::Io(_)));
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        assert!(!Path::new(&tmp).exists());
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    fn snapshot_of_one_entry() -> Vec<u8> {
+        let mut memoized = Memoizer::new(|s: String| s.len() as u64);
+        memoized.call("hello".to_string());
+        let mut bytes = Vec::new();
+        memoized.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn load(bytes: &[u8]) -> Result<usize, SnapshotError> {
+        Memoizer::new(|s: String| s.len() as u64).load_from(bytes)
+    }
+
+    #[test]
+    fn test_detects_bad_snapshots() {
+        let good = snapshot_of_one_entry();
+        assert_eq!(load(&good).unwrap(), 1);
+
+        assert!(matches!(
+            load(b"not a snapshot"),
+            Err(SnapshotError::BadMagic)
+        ));
+        assert!(matches!(load(&good[..4]), Err(SnapshotError::Truncated)));
+        assert!(matches!(load(&good[..12]), Err(SnapshotError::Truncated)));
+
+        let mut newer = good.clone();
+        newer[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&2u16.to_le_bytes());
+        assert!(matches!(
+            load(&newer),
+            Err(SnapshotError::UnsupportedVersion {
+                found: 2,
+                expected: FORMAT_VERSION
+            })
+        ));
+
+        let mut flipped = good.clone();
+        flipped[HEADER_LEN + 9] ^= 0x20;
+        assert!(matches!(
+            load(&flipped),
+            Err(SnapshotError::ChecksumMismatch)
+        ));
+
+        assert!(matches!(
+            load(&good[..good.len() - 1]),
+            Err(SnapshotError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_detects_type_mismatch() {
+        // a well-formed snapshot, but of (String, u64) entries
+        let good = snapshot_of_one_entry();
+        let mut memoized = Memoizer::new(|b: bool| b);
+        let err = memoized.load_from(&good[..]).unwrap_err();
+        assert!(matches!(err, SnapshotError::Corrupt(_)));
+        assert!(memoized.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_entry_count_beyond_input() {
+        // zero-sized entries never run out of input, so this would spin
+        // through u64::MAX of them
+        let mut bytes = encode_snapshot::<(), ()>(std::iter::empty());
+        bytes.truncate(HEADER_LEN);
+        bytes[MAGIC.len() + 2..HEADER_LEN].copy_from_slice(&u64::MAX.to_le_bytes());
+        let sum = checksum(&bytes);
+        sum.encode(&mut bytes);
+
+        let mut memoized = Memoizer::new(|()| ());
+        let err = memoized.load_from(&bytes[..]).unwrap_err();
+        assert!(matches!(
+            err,
+            SnapshotError::Corrupt(DecodeError::Invalid("entry count"))
+        ));
+
+        // a single zero-sized entry is fine though
+        let mut one = Memoizer::new(|()| ());
+        one.call(());
+        let mut bytes = Vec::new();
+        one.save_to(&mut bytes).unwrap();
+        assert_eq!(memoized.load_from(&bytes[..]).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_rejects_zero_sized_vec_length_beyond_input() {
+        let mut huge = Vec::new();
+        u64::MAX.encode(&mut huge);
+        assert_eq!(
+            Vec::<()>::decode(&mut &huge[..]),
+            Err(DecodeError::Invalid("length"))
+        );
+
+        // ... also when nested in a snapshot w/ a valid checksum
+        let mut memoized = Memoizer::new(|()| Vec::<()>::new());
+        let mut bytes = encode_snapshot::<(), Vec<()>>(std::iter::empty());
+        bytes.truncate(HEADER_LEN);
+        bytes[MAGIC.len() + 2..HEADER_LEN].copy_from_slice(&1u64.to_le_bytes());
+        u64::MAX.encode(&mut bytes);
+        let sum = checksum(&bytes);
+        sum.encode(&mut bytes);
+        assert!(matches!(
+            memoized.load_from(&bytes[..]),
+            Err(SnapshotError::Corrupt(DecodeError::Invalid("length")))
+        ));
+
+        roundtrip(vec![()]);
+        roundtrip(Vec::<()>::new());
+    }
+
+    #[test]
+    fn test_missing_file_is_an_io_error() {
+        let mut memoized = Memoizer::new(fib);
+        let err = memoized.load(temp_path("missing")).unwrap_err();
+        assert!(matches!(err, SnapshotError::Io(e) if e.kind() == io::ErrorKind::NotFound));
+    }
+}
@@ -0,0 +1,164 @@
+// Thread-safe counterpart to ch2's Memoizer: `call` takes `&self`, so one
+// memoizer can be shared between threads (e.g. via `Arc` or scoped threads).
+//
+// The cache is split into independently locked shards to cut contention.
+// Each entry is a `OnceLock` that's inserted (under the shard lock) before
+// the value is computed (outside of it): concurrent first requests for a key
+// all get the same cell, and `OnceLock` makes all but one of them wait for
+// that one's result, so each key is computed at most once.
+
+#![allow(unused)]
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+type Shard<T, U> = Mutex<HashMap<T, Arc<OnceLock<U>>>>;
+
+pub struct SyncMemoizer<T, U> {
+    f: Box<dyn Fn(T) -> U + Send + Sync>,
+    shards: Box<[Shard<T, U>]>,
+    hasher: RandomState,
+}
+
+impl<T: Clone + Eq + Hash, U: Clone> SyncMemoizer<T, U> {
+    // a few shards per core
+    pub fn new(f: impl Fn(T) -> U + Send + Sync + 'static) -> Self {
+        let cores = thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_shards(f, (cores * 4).next_power_of_two())
+    }
+
+    pub fn with_shards(f: impl Fn(T) -> U + Send + Sync + 'static, shards: usize) -> Self {
+        assert!(shards > 0, "need at least one shard");
+        Self {
+            f: Box::new(f),
+            shards: (0..shards).map(|_| Mutex::new(HashMap::new())).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn shard(&self, arg: &T) -> &Shard<T, U> {
+        let i = self.hasher.hash_one(arg) as usize % self.shards.len();
+        &self.shards[i]
+    }
+
+    pub fn call(&self, arg: T) -> U {
+        let cell = {
+            // a panic in `f` happens outside the lock, so it can't poison it
+            let mut shard = self.shard(&arg).lock().unwrap();
+            Arc::clone(shard.entry(arg.clone()).or_default())
+        };
+        cell.get_or_init(|| (self.f)(arg)).clone()
+    }
+
+    // cached value, if any, w/o computing it
+    pub fn get(&self, arg: &T) -> Option<U> {
+        let shard = self.shard(arg).lock().unwrap();
+        shard.get(arg)?.get().cloned()
+    }
+
+    // number of keys that have been requested (some may still be computing)
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+    use std::time::Duration;
+
+    fn fib(n: u32) -> u32 {
+        match n {
+            0 | 1 => n,
+            _ => fib(n - 1) + fib(n - 2),
+        }
+    }
+
+    #[test]
+    fn test_fibonacci_correctness() {
+        let fib_memoized = SyncMemoizer::new(fib);
+        assert_eq!(8, fib_memoized.call(6));
+        assert_eq!(Some(8), fib_memoized.get(&6));
+        assert_eq!(None, fib_memoized.get(&7));
+    }
+
+    #[test]
+    fn test_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SyncMemoizer<String, Vec<u8>>>();
+    }
+
+    // every thread asks for every key at (about) the same time; each key must
+    // still only be computed once
+    #[test]
+    fn test_concurrent_first_requests_compute_once() {
+        const THREADS: usize = 8;
+        const KEYS: usize = 64;
+
+        let counts: Arc<Vec<AtomicUsize>> =
+            Arc::new((0..KEYS).map(|_| AtomicUsize::new(0)).collect());
+        let counted = Arc::clone(&counts);
+        let memoized = SyncMemoizer::with_shards(
+            move |n: usize| {
+                counted[n].fetch_add(1, Ordering::SeqCst);
+                // widen the race window
+                thread::sleep(Duration::from_micros(200));
+                n * n
+            },
+            4,
+        );
+        let barrier = Barrier::new(THREADS);
+
+        thread::scope(|s| {
+            for t in 0..THREADS {
+                let (memoized, barrier) = (&memoized, &barrier);
+                s.spawn(move || {
+                    barrier.wait();
+                    // start at a different key per thread to mix things up
+                    for i in 0..KEYS {
+                        let n = (i + t * 7) % KEYS;
+                        assert_eq!(memoized.call(n), n * n);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(memoized.len(), KEYS);
+        for (n, count) in counts.iter().enumerate() {
+            assert_eq!(
+                count.load(Ordering::SeqCst),
+                1,
+                "key {} computed more than once",
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn test_panicking_computation_leaves_memoizer_usable() {
+        let memoized = SyncMemoizer::with_shards(
+            |n: u32| {
+                if n == 0 {
+                    panic!("boom")
+                }
+                n
+            },
+            1,
+        );
+        thread::scope(|s| {
+            assert!(s.spawn(|| memoized.call(0)).join().is_err());
+        });
+        // the shard lock isn't poisoned and other keys still work
+        assert_eq!(memoized.call(1), 1);
+        assert_eq!(memoized.get(&0), None);
+    }
+}
@@ -0,0 +1,413 @@
+// <https://bartoszmilewski.com/2014/12/23/kleisli-categories/>
+//
+// ch4 composes `A -> Option<B>` and `A -> Writer<B>` functions w/ two
+// hand-written `compose`s. Both are the same thing for different effects:
+// all an effect needs to have a Kleisli category is a way to wrap a plain
+// value (`pure`, the identity morphism) and to feed a wrapped value into the
+// next function (`bind`), i.e. to be a monad.
+//
+// Rust has no higher-kinded types, so a monad is given by a marker type
+// (`OptionM`, `VecM`, ...) naming the type constructor `M<_>`, as done for
+// `Category`. Monadic functions have to be 'static: `State` defers running
+// them until it's given a state.
+
+#![allow(unused)]
+
+use std::marker::PhantomData;
+use std::ops::Shr;
+use std::rc::Rc;
+
+use crate::ch3::Monoid;
+use crate::ch4::writer::{self, Writer};
+
+pub trait Monad: 'static {
+    type M<A>;
+
+    fn pure<A: 'static>(a: A) -> Self::M<A>;
+
+    fn bind<A: 'static, B: 'static>(
+        m: Self::M<A>,
+        f: impl Fn(A) -> Self::M<B> + 'static,
+    ) -> Self::M<B>;
+}
+
+// partial functions
+pub struct OptionM;
+
+impl Monad for OptionM {
+    type M<A> = Option<A>;
+
+    fn pure<A: 'static>(a: A) -> Option<A> {
+        Some(a)
+    }
+
+    fn bind<A: 'static, B: 'static>(m: Option<A>, f: impl Fn(A) -> Option<B>) -> Option<B> {
+        m.and_then(f)
+    }
+}
+
+// partial functions that say why
+pub struct ResultM<E>(PhantomData<E>);
+
+impl<E: 'static> Monad for ResultM<E> {
+    type M<A> = Result<A, E>;
+
+    fn pure<A: 'static>(a: A) -> Result<A, E> {
+        Ok(a)
+    }
+
+    fn bind<A: 'static, B: 'static>(
+        m: Result<A, E>,
+        f: impl Fn(A) -> Result<B, E>,
+    ) -> Result<B, E> {
+        m.and_then(f)
+    }
+}
+
+// nondeterminism: every result of f for every value
+pub struct VecM;
+
+impl Monad for VecM {
+    type M<A> = Vec<A>;
+
+    fn pure<A: 'static>(a: A) -> Vec<A> {
+        vec![a]
+    }
+
+    fn bind<A: 'static, B: 'static>(m: Vec<A>, f: impl Fn(A) -> Vec<B>) -> Vec<B> {
+        m.into_iter().flat_map(f).collect()
+    }
+}
+
+// logging, w/ ch4's Writer and any monoid log (String by default)
+pub struct WriterM<W = String>(PhantomData<W>);
+
+impl<W: Monoid + 'static> Monad for WriterM<W> {
+    type M<A> = Writer<A, W>;
+
+    fn pure<A: 'static>(a: A) -> Writer<A, W> {
+        writer::identity_morphism(a)
+    }
+
+    fn bind<A: 'static, B: 'static>(
+        m: Writer<A, W>,
+        f: impl Fn(A) -> Writer<B, W>,
+    ) -> Writer<B, W> {
+        m.and_then(f)
+    }
+}
+
+// a computation threading a state S through, yielding an A
+pub struct State<S, A>(Box<dyn FnOnce(S) -> (A, S)>);
+
+impl<S, A> State<S, A> {
+    pub fn new(f: impl FnOnce(S) -> (A, S) + 'static) -> Self {
+        State(Box::new(f))
+    }
+
+    // the result and the final state
+    pub fn run(self, s: S) -> (A, S) {
+        (self.0)(s)
+    }
+
+    pub fn eval(self, s: S) -> A {
+        self.run(s).0
+    }
+
+    pub fn exec(self, s: S) -> S {
+        self.run(s).1
+    }
+}
+
+impl<S: Clone + 'static> State<S, S> {
+    pub fn get() -> Self {
+        State::new(|s: S| (s.clone(), s))
+    }
+}
+
+impl<S: 'static> State<S, ()> {
+    pub fn put(s: S) -> Self {
+        State::new(|_| ((), s))
+    }
+
+    pub fn modify(f: impl FnOnce(S) -> S + 'static) -> Self {
+        State::new(|s| ((), f(s)))
+    }
+}
+
+pub struct StateM<S>(PhantomData<S>);
+
+impl<S: 'static> Monad for StateM<S> {
+    type M<A> = State<S, A>;
+
+    fn pure<A: 'static>(a: A) -> State<S, A> {
+        State(Box::new(|s| (a, s)))
+    }
+
+    fn bind<A: 'static, B: 'static>(
+        m: State<S, A>,
+        f: impl Fn(A) -> State<S, B> + 'static,
+    ) -> State<S, B> {
+        State::new(move |s| {
+            let (a, s) = m.run(s);
+            f(a).run(s)
+        })
+    }
+}
+
+// f then g in K's Kleisli category (g . f, like ch1::compose). K can't be
+// inferred from the closures, so it's given explicitly:
+//   kleisli_compose::<OptionM, _, _, _>(safe_reciprocal, safe_root)
+pub fn kleisli_compose<K: Monad, A, B: 'static, C: 'static>(
+    f: impl Fn(A) -> K::M<B>,
+    g: impl Fn(B) -> K::M<C> + 'static,
+) -> impl Fn(A) -> K::M<C> {
+    // each bind may hold onto g (State's does), so it's shared
+    let g = Rc::new(g);
+    move |a| {
+        let g = Rc::clone(&g);
+        K::bind(f(a), move |b| g(b))
+    }
+}
+
+// a Kleisli arrow A -> M<B>, composable w/ `>>` for Haskell's `>=>`:
+//   let f = arrow::<OptionM, _, _>(safe_reciprocal) >> arrow(safe_root);
+pub struct KleisliArrow<K: Monad, A, B>(Rc<dyn Fn(A) -> K::M<B>>);
+
+pub fn arrow<K: Monad, A, B>(f: impl Fn(A) -> K::M<B> + 'static) -> KleisliArrow<K, A, B> {
+    KleisliArrow(Rc::new(f))
+}
+
+impl<K: Monad, A, B> KleisliArrow<K, A, B> {
+    pub fn apply(&self, a: A) -> K::M<B> {
+        (self.0)(a)
+    }
+}
+
+impl<K: Monad, A: 'static> KleisliArrow<K, A, A> {
+    pub fn id() -> Self {
+        arrow(K::pure)
+    }
+}
+
+impl<K: Monad, A, B> Clone for KleisliArrow<K, A, B> {
+    fn clone(&self) -> Self {
+        KleisliArrow(Rc::clone(&self.0))
+    }
+}
+
+impl<K: Monad, A: 'static, B: 'static, C: 'static> Shr<KleisliArrow<K, B, C>>
+    for KleisliArrow<K, A, B>
+{
+    type Output = KleisliArrow<K, A, C>;
+
+    fn shr(self, g: KleisliArrow<K, B, C>) -> KleisliArrow<K, A, C> {
+        let f = self.0;
+        arrow(kleisli_compose::<K, _, _, _>(
+            move |a| f(a),
+            move |b| g.apply(b),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrary::samples;
+    use crate::ch4::optional::{safe_reciprocal, safe_root, safe_root_reciprocal};
+    use crate::ch4::writer::{to_words, upcase};
+    use std::fmt::Debug;
+
+    // the monad laws, pointwise:
+    //   bind(pure(a), f) == f(a)
+    //   bind(m, pure) == m
+    //   bind(bind(m, f), g) == bind(m, |x| bind(f(x), g))
+    // w/ `run` turning M<_> into something comparable
+    fn check_monad_laws<K, A, R>(
+        a: A,
+        m: impl Fn() -> K::M<A>,
+        f: impl Fn(A) -> K::M<A> + Clone + 'static,
+        g: impl Fn(A) -> K::M<A> + Clone + 'static,
+        run: impl Fn(K::M<A>) -> R,
+    ) where
+        K: Monad,
+        A: Clone + 'static,
+        R: PartialEq + Debug,
+    {
+        assert_eq!(run(K::bind(K::pure(a.clone()), f.clone())), run(f(a)));
+        assert_eq!(run(K::bind(m(), K::pure)), run(m()));
+        let (f2, g2) = (f.clone(), g.clone());
+        assert_eq!(
+            run(K::bind(K::bind(m(), f), g)),
+            run(K::bind(m(), move |x| K::bind(f2(x), g2.clone())))
+        );
+    }
+
+    #[test]
+    fn test_monad_laws() {
+        for (a, b) in samples::<(i32, i32)>(23, 100) {
+            let half = |x: i32| (x % 2 == 0).then_some(x / 2);
+            let dec = |x: i32| (x > 0).then(|| x - 1);
+            check_monad_laws::<OptionM, _, _>(a, move || half(b), half, dec, |m| m);
+
+            let pos = |x: i32| {
+                if x > 0 {
+                    Ok(x)
+                } else {
+                    Err(format!("{} <= 0", x))
+                }
+            };
+            let small = |x: i32| {
+                if x < 50 {
+                    Ok(x * 2)
+                } else {
+                    Err("too big".to_string())
+                }
+            };
+            check_monad_laws::<ResultM<String>, _, _>(a, move || pos(b), pos, small, |m| m);
+
+            let around = |x: i32| vec![x - 1, x, x + 1];
+            let divisors = |x: i32| (1..=x.abs().min(20)).filter(|d| x % d == 0).collect();
+            check_monad_laws::<VecM, _, _>(a, move || around(b), around, divisors, |m| m);
+
+            let logged = |x: i32| Writer(x + 1, "+1 ".to_string());
+            let shown = |x: i32| Writer(x, format!("{} ", x));
+            check_monad_laws::<WriterM, _, _>(a, move || shown(b), logged, shown, |m| m);
+
+            // states are compared by running them
+            let add_to_state = |x: i32| State::new(move |s: i32| (x, s + x));
+            let swap = |x: i32| State::new(move |s: i32| (s, x));
+            check_monad_laws::<StateM<i32>, _, _>(
+                a,
+                move || swap(b),
+                add_to_state,
+                swap,
+                |m| m.run(7),
+            );
+        }
+    }
+
+    #[test]
+    fn test_option_kleisli_compose() {
+        let f = kleisli_compose::<OptionM, _, _, _>(safe_reciprocal, safe_root);
+        for x in [-4.0, 0.0, 0.25, 4.0] {
+            assert_eq!(f(x), safe_root_reciprocal(x));
+        }
+
+        // A -> Option<B> then B -> Option<C>, w/ A, B and C all different
+        let parse = |s: &str| s.parse::<u32>().ok();
+        let halve = |n: u32| n.is_multiple_of(2).then_some(n as f64 / 2.0);
+        let f = kleisli_compose::<OptionM, _, _, _>(parse, halve);
+        assert_eq!(f("42"), Some(21.0));
+        assert_eq!(f("41"), None);
+    }
+
+    #[test]
+    fn test_result_kleisli_compose() {
+        let parse = |s: &str| s.parse::<i64>().map_err(|e| e.to_string());
+        let non_neg = |n: i64| u64::try_from(n).map_err(|_| format!("{} is negative", n));
+        let f = kleisli_compose::<ResultM<String>, _, _, _>(parse, non_neg);
+        assert_eq!(f("42"), Ok(42));
+        assert_eq!(f("-42"), Err("-42 is negative".to_string()));
+        assert_eq!(f("x"), Err("invalid digit found in string".to_string()));
+    }
+
+    #[test]
+    fn test_vec_kleisli_compose() {
+        // a knight's possible positions after two moves, from a corner
+        let moves = |(x, y): (i8, i8)| -> Vec<(i8, i8)> {
+            [
+                (1, 2),
+                (2, 1),
+                (-1, 2),
+                (-2, 1),
+                (1, -2),
+                (2, -1),
+                (-1, -2),
+                (-2, -1),
+            ]
+            .into_iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter(|&(x, y)| (0..8).contains(&x) && (0..8).contains(&y))
+            .collect()
+        };
+        let twice = kleisli_compose::<VecM, _, _, _>(moves, moves);
+        let mut reachable = twice((0, 0));
+        // one path for each pair of moves, some ending on the same square
+        assert_eq!(reachable.len(), 12);
+        reachable.sort();
+        reachable.dedup();
+        assert_eq!(reachable.len(), 10);
+        assert!(reachable.contains(&(0, 0)));
+        assert!(reachable.contains(&(2, 4)));
+    }
+
+    #[test]
+    fn test_writer_kleisli_compose_matches_ch4() {
+        let f = kleisli_compose::<WriterM, _, _, _>(upcase, to_words);
+        let g = writer::compose(upcase, to_words);
+        let s = "kleisli composition";
+        assert_eq!(f(s.to_string()), g(s.to_string()));
+    }
+
+    #[test]
+    fn test_state_kleisli_compose() {
+        // label each item w/ a fresh number drawn from the state
+        let fresh =
+            |name: &'static str| State::new(move |n: u32| (format!("{}{}", name, n), n + 1));
+        let pair = kleisli_compose::<StateM<u32>, _, _, _>(fresh, move |a: String| {
+            StateM::bind(fresh("y"), move |b| StateM::pure((a.clone(), b)))
+        });
+        assert_eq!(pair("x").run(5), (("x5".to_string(), "y6".to_string()), 7));
+
+        let count = StateM::bind(State::modify(|n: u32| n * 10), |()| State::get());
+        assert_eq!(count.run(4), (40, 40));
+        assert_eq!(State::put(3).exec(1), 3);
+        assert_eq!(State::<u32, u32>::get().eval(9), 9);
+    }
+
+    #[test]
+    fn test_fish_operator() {
+        let f = arrow::<OptionM, _, _>(safe_reciprocal) >> arrow(safe_root);
+        assert_eq!(f.apply(4.0), Some(0.5));
+        assert_eq!(f.apply(0.0), None);
+
+        let f = arrow::<WriterM, _, _>(upcase) >> KleisliArrow::id() >> arrow(to_words);
+        assert_eq!(
+            f.apply("fish op".to_string()),
+            Writer(
+                vec!["FISH".to_string(), "OP".to_string()],
+                "upcase to_words ".to_string()
+            )
+        );
+
+        let incr = arrow::<StateM<u32>, _, _>(|x: u32| State::new(move |calls| (x + 1, calls + 1)));
+        let thrice = incr.clone() >> incr.clone() >> incr;
+        assert_eq!(thrice.apply(0).run(0), (3, 3));
+
+        let spread = arrow::<VecM, i32, i32>(|x| vec![x, -x]);
+        assert_eq!((spread.clone() >> spread).apply(1), vec![1, -1, -1, 1]);
+    }
+
+    #[test]
+    fn test_kleisli_category_laws() {
+        use crate::categories::KleisliCat;
+        use crate::category_laws::check_category_laws;
+
+        let result = check_category_laws::<KleisliCat<VecM>, _, _, _, _>(
+            || Box::new(|x: i32| vec![x, x * 10]),
+            || Box::new(|x: i32| (0..x.rem_euclid(3)).map(|i| x + i).collect()),
+            || Box::new(|x: i32| vec![x.to_string()]),
+            -20..20,
+        );
+        assert_eq!(result, Ok(()));
+
+        let result = check_category_laws::<KleisliCat<ResultM<String>>, _, _, _, _>(
+            || Box::new(|s: &str| s.parse::<u8>().map_err(|e| e.to_string())),
+            || Box::new(|n: u8| n.checked_mul(2).ok_or("overflow".to_string())),
+            || Box::new(|n: u8| Ok(n as char)),
+            ["", "0", "65", "200", "x"],
+        );
+        assert_eq!(result, Ok(()));
+    }
+}
@@ -0,0 +1,188 @@
+// <https://bartoszmilewski.com/2014/12/05/categories-great-and-small/>
+//
+// Both ways between monoids and categories:
+// - a monoid is a category w/ a single object, whose morphisms are the
+//   monoid's elements: composition is `combine`, the identity is `empty`.
+//   `MonoidCat<M>` is that category; as there's only one object, `Hom<A, B>`
+//   is M whatever A and B are, and "running" a morphism just yields it.
+// - conversely the endomorphisms A -> A of any one object form a monoid
+//   under composition, `Endo<A>`.
+
+#![allow(unused)]
+
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use crate::categories::Category;
+use crate::ch1;
+use crate::ch3::{Monoid, Semigroup};
+
+pub struct MonoidCat<M>(PhantomData<M>);
+
+impl<M: Monoid + Clone + 'static> Category for MonoidCat<M> {
+    type Hom<A: 'static, B: 'static> = M;
+    type Out<B> = M;
+
+    fn id<A: 'static>() -> M {
+        M::empty()
+    }
+
+    fn compose<A: 'static, B: 'static, C: 'static>(f: M, g: M) -> M {
+        f.combine(g)
+    }
+
+    fn apply<A: 'static, B: 'static>(f: &M, _: A) -> M {
+        f.clone()
+    }
+}
+
+// a function A -> A. combining is composition in ch1's (diagrammatic) order:
+// `f.combine(g)` applies f first, then g
+#[derive(Clone)]
+pub struct Endo<A>(Rc<dyn Fn(A) -> A>);
+
+impl<A: 'static> Endo<A> {
+    pub fn new(f: impl Fn(A) -> A + 'static) -> Self {
+        Endo(Rc::new(f))
+    }
+
+    pub fn apply(&self, a: A) -> A {
+        (self.0)(a)
+    }
+}
+
+impl<A: 'static> Semigroup for Endo<A> {
+    fn combine(self, other: Self) -> Self {
+        let (f, g) = (self.0, other.0);
+        Endo::new(ch1::compose(move |a| f(a), move |a| g(a)))
+    }
+}
+
+impl<A: 'static> Monoid for Endo<A> {
+    fn empty() -> Self {
+        Endo::new(ch1::id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrary::samples;
+    use crate::category_laws::{check_category_laws, Law};
+    use crate::ch3::{Max, Sum};
+    use crate::finite_types::{ext_eq, Finite, FnTable};
+    use crate::monoid_fold::MonoidIterator;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn test_monoids_are_categories() {
+        for (a, b, c) in samples::<(String, String, String)>(3, 100) {
+            let result = check_category_laws::<MonoidCat<String>, (), (), (), ()>(
+                || a.clone(),
+                || b.clone(),
+                || c.clone(),
+                [()],
+            );
+            assert_eq!(result, Ok(()));
+        }
+        for (a, b, c) in samples::<(u16, u16, u16)>(5, 100) {
+            let result = check_category_laws::<MonoidCat<(Sum<u32>, Max<u16>)>, (), (), (), ()>(
+                || (Sum(a as u32), Max(a)),
+                || (Sum(b as u32), Max(b)),
+                || (Sum(c as u32), Max(c)),
+                [()],
+            );
+            assert_eq!(result, Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_composition_is_combine() {
+        type C = MonoidCat<Vec<u8>>;
+        let f = C::compose::<u8, u8, u8>(vec![1], C::compose::<u8, u8, u8>(C::id::<u8>(), vec![2]));
+        assert_eq!(C::apply::<u8, u8>(&f, 0), vec![1, 2]);
+    }
+
+    // a lawless "monoid" (whose empty isn't a unit) is caught as a category
+    #[derive(Debug, Clone, PartialEq)]
+    struct Average(f64);
+
+    impl Semigroup for Average {
+        fn combine(self, other: Self) -> Self {
+            Average((self.0 + other.0) / 2.0)
+        }
+    }
+
+    impl Monoid for Average {
+        fn empty() -> Self {
+            Average(0.0)
+        }
+    }
+
+    #[test]
+    fn test_lawless_monoid_is_not_a_category() {
+        let result = check_category_laws::<MonoidCat<Average>, (), (), (), ()>(
+            || Average(4.0),
+            || Average(2.0),
+            || Average(8.0),
+            [()],
+        );
+        assert_eq!(result.unwrap_err().law, Law::LeftIdentity);
+    }
+
+    #[test]
+    fn test_endo_composes_in_order() {
+        let incr = Endo::new(|x: i32| x + 1);
+        let double = Endo::new(|x: i32| x * 2);
+        assert_eq!(incr.clone().combine(double.clone()).apply(5), 12);
+        assert_eq!(double.combine(incr).apply(5), 11);
+        assert_eq!(Endo::<i32>::empty().apply(5), 5);
+
+        let pipeline = ["a", "b", "c"]
+            .into_iter()
+            .fold_map(|s| Endo::new(move |acc: String| acc + s));
+        assert_eq!(pipeline.apply(">".to_string()), ">abc");
+    }
+
+    // exhaustively, over every endomorphism of a finite type
+    fn check_endo_laws<A: Finite + 'static>() {
+        let endos = || FnTable::<A, A>::all().map(|t| Endo::new(t.into_fn()));
+        for f in endos() {
+            let (l, r) = (
+                Endo::empty().combine(f.clone()),
+                f.clone().combine(Endo::empty()),
+            );
+            assert!(ext_eq(|a| l.apply(a), |a| f.apply(a)));
+            assert!(ext_eq(|a| r.apply(a), |a| f.apply(a)));
+            for g in endos() {
+                for h in endos() {
+                    let lhs = f.clone().combine(g.clone()).combine(h.clone());
+                    let rhs = f.clone().combine(g.clone().combine(h));
+                    assert!(ext_eq(|a| lhs.apply(a), |a| rhs.apply(a)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_endo_monoid_laws() {
+        check_endo_laws::<bool>();
+        check_endo_laws::<Ordering>();
+        check_endo_laws::<Option<bool>>();
+    }
+
+    #[test]
+    fn test_endo_monoid_as_a_category() {
+        // round trip: Endo's monoid, viewed as a one-object category again;
+        // morphisms are compared by their tables
+        type C = MonoidCat<Endo<bool>>;
+        let not = || Endo::new(|b: bool| !b);
+        let f = C::compose::<(), (), ()>(C::id::<()>(), not());
+        let g = C::compose::<(), (), ()>(not(), not());
+        assert_eq!(
+            FnTable::tabulate(|b| C::apply::<(), ()>(&f, ()).apply(b)),
+            FnTable::tabulate(|b: bool| !b)
+        );
+        assert!(ext_eq(|b| g.apply(b), ch1::id));
+    }
+}
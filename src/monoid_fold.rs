@@ -0,0 +1,212 @@
+// Folding w/ monoids: `mconcat` smashes a sequence of monoid values together,
+// `fold_map` maps each item into a monoid first. Since `combine` is
+// associative, a slice can also be split in halves, folded on separate
+// threads and the halves' results combined, w/o changing the result (the
+// order of the items is kept, so this doesn't need commutativity).
+
+#![allow(unused)]
+
+use std::num::NonZeroUsize;
+use std::thread;
+
+use crate::ch3::{Monoid, Semigroup};
+
+// below this many items per thread, the parallel fold isn't worth the spawns
+pub const DEFAULT_MIN_CHUNK: usize = 4096;
+
+pub trait MonoidIterator: Iterator + Sized {
+    fn mconcat(self) -> Self::Item
+    where
+        Self::Item: Monoid,
+    {
+        self.fold(Self::Item::empty(), Semigroup::combine)
+    }
+
+    fn fold_map<M: Monoid>(self, f: impl FnMut(Self::Item) -> M) -> M {
+        self.map(f).mconcat()
+    }
+}
+
+impl<I: Iterator> MonoidIterator for I {}
+
+fn available_threads() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+// `items.iter().fold_map(f)`, split across up to one thread per core
+pub fn par_fold_map<T: Sync, M: Monoid + Send>(items: &[T], f: impl Fn(&T) -> M + Sync) -> M {
+    let chunk = items
+        .len()
+        .div_ceil(available_threads())
+        .max(DEFAULT_MIN_CHUNK);
+    par_fold_map_chunked(items, chunk, f)
+}
+
+// as above, but halving until there are at most `min_chunk` items left, which
+// are then folded sequentially. Whatever `min_chunk`, no more threads than
+// cores are used: once they're all taken, halves are folded sequentially too
+pub fn par_fold_map_chunked<T: Sync, M: Monoid + Send>(
+    items: &[T],
+    min_chunk: usize,
+    f: impl Fn(&T) -> M + Sync,
+) -> M {
+    // the calling thread does its share
+    fold_with_spare_threads(items, min_chunk, available_threads() - 1, f)
+}
+
+fn fold_with_spare_threads<T: Sync, M: Monoid + Send>(
+    items: &[T],
+    min_chunk: usize,
+    spare: usize,
+    f: impl Fn(&T) -> M + Sync,
+) -> M {
+    assert!(min_chunk > 0, "chunks must be non-empty");
+    thread::scope(|s| split_fold(s, items, min_chunk, spare, &f))
+}
+
+// `spare` is how many more threads this call (and its own splits) may spawn
+fn split_fold<'scope, 'env, T: Sync, M: Monoid + Send + 'scope>(
+    s: &'scope thread::Scope<'scope, 'env>,
+    items: &'env [T],
+    min_chunk: usize,
+    spare: usize,
+    f: &'env (impl Fn(&T) -> M + Sync),
+) -> M {
+    if items.len() <= min_chunk || spare == 0 {
+        return items.iter().fold_map(f);
+    }
+    let (left, right) = items.split_at(items.len() / 2);
+    // left half on a new thread, right half on this one, sharing out what's
+    // left of the budget between them
+    let (left_spare, right_spare) = ((spare - 1) / 2, spare - 1 - (spare - 1) / 2);
+    let left = s.spawn(move || split_fold(s, left, min_chunk, left_spare, f));
+    let right = split_fold(s, right, min_chunk, right_spare, f);
+    left.join().unwrap().combine(right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::BenchConfig;
+    use crate::ch3::{Max, Min, Sum};
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Mutex;
+
+    fn word_counts(text: &str) -> HashMap<&str, Sum<usize>> {
+        text.split_whitespace()
+            .fold_map(|w| HashMap::from([(w, Sum(1))]))
+    }
+
+    #[test]
+    fn test_mconcat() {
+        let words = ["mon", "oid", "al"].map(String::from);
+        assert_eq!(words.into_iter().mconcat(), "monoidal");
+        assert_eq!(std::iter::empty::<Sum<u8>>().mconcat(), Sum(0));
+        assert_eq!(
+            vec![Some(Max(3)), None, Some(Max(7))].into_iter().mconcat(),
+            Some(Max(7))
+        );
+    }
+
+    #[test]
+    fn test_fold_map_word_counts() {
+        let counts = word_counts("the cat and the hat and the bat");
+        assert_eq!(counts["the"], Sum(3));
+        assert_eq!(counts["and"], Sum(2));
+        assert_eq!(counts["cat"], Sum(1));
+        assert_eq!(counts.len(), 5);
+    }
+
+    #[test]
+    fn test_fold_map_stats_summary() {
+        let summary = |x: &u32| (Sum(1), Sum(*x as u64), Min(*x), Max(*x));
+        let (count, total, min, max) = [4, 8, 15, 16, 23, 42].iter().fold_map(summary);
+        assert_eq!((count.0, total.0, min.0, max.0), (6, 108, 4, 42));
+    }
+
+    #[test]
+    fn test_par_fold_map_matches_sequential() {
+        let xs: Vec<u32> = (0..10_000).collect();
+        let sum = |x: &u32| Sum(*x as u64);
+        let expected = xs.iter().fold_map(sum);
+        assert_eq!(par_fold_map(&xs, sum), expected);
+        for chunk in [64, 1000, 4096, 10_000, 20_000] {
+            assert_eq!(par_fold_map_chunked(&xs, chunk, sum), expected);
+        }
+    }
+
+    #[test]
+    fn test_par_fold_map_keeps_order() {
+        // string concatenation isn't commutative
+        let letters: Vec<char> = ('a'..='z').cycle().take(10_000).collect();
+        let expected: String = letters.iter().collect();
+        assert_eq!(
+            par_fold_map_chunked(&letters, 256, |c| c.to_string()),
+            expected
+        );
+        assert_eq!(
+            par_fold_map_chunked(&[] as &[char], 256, |c| c.to_string()),
+            ""
+        );
+    }
+
+    // how many distinct threads fold `xs` w/ `spare` extra threads to spawn;
+    // a tiny chunk size would mean a thread per item w/o the budget
+    fn threads_used(spare: usize) -> usize {
+        let seen = Mutex::new(HashSet::new());
+        let xs: Vec<u32> = (0..100_000).collect();
+        let total = fold_with_spare_threads(&xs, 1, spare, |x| {
+            seen.lock().unwrap().insert(thread::current().id());
+            Sum(*x as u64)
+        });
+        assert_eq!(total, Sum(99_999 * 100_000 / 2));
+        seen.into_inner().unwrap().len()
+    }
+
+    #[test]
+    fn test_par_fold_map_threads_are_bounded() {
+        assert_eq!(threads_used(0), 1);
+        assert_eq!(threads_used(1), 2);
+        assert_eq!(threads_used(7), 8);
+
+        let seen = Mutex::new(HashSet::new());
+        let xs: Vec<u32> = (0..100_000).collect();
+        par_fold_map_chunked(&xs, 1, |x| {
+            seen.lock().unwrap().insert(thread::current().id());
+            Sum(*x as u64)
+        });
+        assert!(seen.into_inner().unwrap().len() <= available_threads());
+    }
+
+    #[test]
+    fn test_par_word_counts() {
+        let lines: Vec<&str> = "the cat and the hat and the bat"
+            .split(' ')
+            .cycle()
+            .take(800)
+            .collect();
+        let counts = par_fold_map_chunked(&lines, 100, |w| HashMap::from([(*w, Sum(1))]));
+        assert_eq!(counts["the"], Sum(300));
+        assert_eq!(counts["bat"], Sum(100));
+    }
+
+    fn big_input() -> Vec<u64> {
+        (0..1_000_000).collect()
+    }
+
+    crate::bench!(
+        bench_fold_map_sequential,
+        BenchConfig::default().samples(50),
+        big_input() => |xs| {
+            xs.iter().fold_map(|x| (Sum(*x), Max(*x)))
+        }
+    );
+
+    crate::bench!(
+        bench_fold_map_parallel,
+        BenchConfig::default().samples(50),
+        big_input() => |xs| {
+            par_fold_map(xs, |x| (Sum(*x), Max(*x)))
+        }
+    );
+}
@@ -0,0 +1,243 @@
+// Inspectable alternative to ch1::compose: a pipeline is a list of named,
+// type-erased stages, so (unlike ch1's opaque `impl Fn`) it can report which
+// stages it contains and trace the value flowing out of each one.
+//
+// The static types are tracked by the `Pipeline<A, B>` phantom parameters;
+// intermediate values travel as `Box<dyn Any>` and are downcast at each stage,
+// which can't fail as `then` only ever glues a B-consuming stage onto a
+// B-producing pipeline.
+
+#![allow(unused)]
+
+use std::any::Any;
+use std::fmt;
+use std::marker::PhantomData;
+
+type ErasedFn = Box<dyn Fn(Box<dyn Any>) -> Box<dyn Any>>;
+
+struct Stage {
+    name: String,
+    f: ErasedFn,
+    // formats this stage's (erased) output for tracing
+    debug: fn(&dyn Any) -> String,
+}
+
+fn debug_any<T: fmt::Debug + 'static>(v: &dyn Any) -> String {
+    format!(
+        "{:?}",
+        v.downcast_ref::<T>().expect("pipeline stage type mismatch")
+    )
+}
+
+pub struct Pipeline<A, B> {
+    stages: Vec<Stage>,
+    _marker: PhantomData<fn(A) -> B>,
+}
+
+// one step of a traced run: the stage's name and its output's Debug repr
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    pub stage: String,
+    pub output: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub input: String,
+    pub steps: Vec<TraceStep>,
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "input: {}", self.input)?;
+        for step in &self.steps {
+            write!(f, "\n{}: {}", step.stage, step.output)?;
+        }
+        Ok(())
+    }
+}
+
+impl<A: 'static> Pipeline<A, A> {
+    // the empty pipeline, i.e. id
+    pub fn identity() -> Self {
+        Self {
+            stages: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A: 'static, B: fmt::Debug + 'static> Pipeline<A, B> {
+    // single-stage pipeline
+    pub fn stage(name: impl Into<String>, f: impl Fn(A) -> B + 'static) -> Self {
+        Pipeline::<A, A>::identity().then(name, f)
+    }
+}
+
+impl<A: 'static, B: 'static> Pipeline<A, B> {
+    pub fn then<C: fmt::Debug + 'static>(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(B) -> C + 'static,
+    ) -> Pipeline<A, C> {
+        self.stages.push(Stage {
+            name: name.into(),
+            f: Box::new(move |x| {
+                let x = x.downcast::<B>().expect("pipeline stage type mismatch");
+                Box::new(f(*x))
+            }),
+            debug: debug_any::<C>,
+        });
+        Pipeline {
+            stages: self.stages,
+            _marker: PhantomData,
+        }
+    }
+
+    // append all of `other`'s stages
+    pub fn then_pipeline<C: 'static>(mut self, other: Pipeline<B, C>) -> Pipeline<A, C> {
+        self.stages.extend(other.stages);
+        Pipeline {
+            stages: self.stages,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn stages(&self) -> Vec<&str> {
+        self.stages.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    pub fn run(&self, arg: A) -> B {
+        let out = self
+            .stages
+            .iter()
+            .fold(Box::new(arg) as Box<dyn Any>, |x, stage| (stage.f)(x));
+        *out.downcast::<B>().expect("pipeline stage type mismatch")
+    }
+
+    // same as `run`, but also records every intermediate value
+    pub fn run_traced(&self, arg: A) -> (B, Trace)
+    where
+        A: fmt::Debug,
+    {
+        let mut trace = Trace {
+            input: format!("{:?}", arg),
+            steps: Vec::with_capacity(self.stages.len()),
+        };
+        let mut x: Box<dyn Any> = Box::new(arg);
+        for stage in &self.stages {
+            x = (stage.f)(x);
+            trace.steps.push(TraceStep {
+                stage: stage.name.clone(),
+                output: (stage.debug)(x.as_ref()),
+            });
+        }
+        let out = *x.downcast::<B>().expect("pipeline stage type mismatch");
+        (out, trace)
+    }
+}
+
+impl<A, B> fmt::Debug for Pipeline<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field(
+                "stages",
+                &self.stages.iter().map(|s| &s.name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ch1::compose;
+
+    fn add_one(x: u32) -> u32 {
+        x + 1
+    }
+
+    fn double(x: u32) -> u32 {
+        x * 2
+    }
+
+    #[test]
+    fn test_pipeline_agrees_with_compose() {
+        let p = Pipeline::stage("add_one", add_one).then("double", double);
+        assert_eq!(p.run(1), compose(add_one, double)(1));
+    }
+
+    #[test]
+    fn test_identity_pipeline() {
+        let p = Pipeline::<&str, &str>::identity();
+        assert!(p.is_empty());
+        assert_eq!(p.run("id"), "id");
+        assert_eq!(p.run_traced("id").1.steps, vec![]);
+    }
+
+    #[test]
+    fn test_pipeline_stage_list() {
+        let p = Pipeline::stage("add_one", add_one)
+            .then("double", double)
+            .then("to_string", |x: u32| x.to_string())
+            .then("len", |s: String| s.len());
+        assert_eq!(p.len(), 4);
+        assert_eq!(p.stages(), vec!["add_one", "double", "to_string", "len"]);
+        assert_eq!(p.run(49), 3);
+    }
+
+    #[test]
+    fn test_then_pipeline() {
+        let front = Pipeline::stage("add_one", add_one);
+        let back = Pipeline::stage("double", double).then("add_one", add_one);
+        let p = front.then_pipeline(back);
+        assert_eq!(p.stages(), vec!["add_one", "double", "add_one"]);
+        assert_eq!(p.run(1), 5);
+        assert_eq!(
+            format!("{:?}", p),
+            r#"Pipeline { stages: ["add_one", "double", "add_one"] }"#
+        );
+    }
+
+    #[test]
+    fn test_run_traced() {
+        let p = Pipeline::stage("parse", |s: &str| s.parse::<i32>())
+            .then("unwrap_or_zero", |r: Result<i32, _>| r.unwrap_or(0))
+            .then("negate", |x: i32| -x);
+
+        let (out, trace) = p.run_traced("42");
+        assert_eq!(out, -42);
+        assert_eq!(trace.input, r#""42""#);
+        assert_eq!(
+            trace.steps,
+            vec![
+                TraceStep {
+                    stage: "parse".to_string(),
+                    output: "Ok(42)".to_string()
+                },
+                TraceStep {
+                    stage: "unwrap_or_zero".to_string(),
+                    output: "42".to_string()
+                },
+                TraceStep {
+                    stage: "negate".to_string(),
+                    output: "-42".to_string()
+                },
+            ]
+        );
+
+        // the surprising value shows up at the stage that produced it
+        let (out, trace) = p.run_traced("forty-two");
+        assert_eq!(out, 0);
+        assert!(trace.steps[0].output.starts_with("Err("));
+        assert_eq!(trace.to_string().lines().last(), Some("negate: 0"));
+    }
+}
@@ -0,0 +1,349 @@
+// A Writer whose log is structured: a forest of timed spans instead of
+// ch4's `"upcase to_words "` string. Each span has a name, start and end
+// times from an injectable `Clock` (a ManualClock in tests), key/value
+// fields, and the spans logged while it was open as children.
+//
+// `Tracer::span` times a computation and nests whatever it logged under the
+// new span; `Tracer::stage` and `Tracer::lift` turn plain and ch4-style
+// String-logging functions into traced ones, which then compose like any
+// other Writer-returning functions. The result renders as indented text for
+// reading, or as JSON lines (one span per line, pre-order) for tools.
+
+#![allow(unused)]
+
+use std::fmt::{self, Display, Write as _};
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::benchmark::json_string;
+use crate::ch3::{Monoid, Semigroup};
+use crate::ch4::writer::Writer;
+use crate::memoize_eviction::Clock;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub name: String,
+    pub start: Duration,
+    pub end: Duration,
+    pub fields: Vec<(String, String)>,
+    pub children: Vec<Span>,
+}
+
+impl Span {
+    pub fn duration(&self) -> Duration {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+// the log: top-level spans in the order they were closed
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Spans(pub Vec<Span>);
+
+impl Semigroup for Spans {
+    fn combine(self, other: Self) -> Self {
+        Spans(self.0.combine(other.0))
+    }
+}
+
+impl Monoid for Spans {
+    fn empty() -> Self {
+        Spans(Vec::new())
+    }
+}
+
+pub type Traced<T> = Writer<T, Spans>;
+
+impl<T> Writer<T, Spans> {
+    // adds a field to the most recently closed top-level span, e.g.
+    //   tracer.stage("parse", parse)(s).field("input_len", s.len())
+    // an empty log (nothing traced yet) is left as it is
+    pub fn field(mut self, key: impl Into<String>, value: impl Display) -> Self {
+        if let Some(span) = self.1 .0.last_mut() {
+            span.fields.push((key.into(), value.to_string()));
+        }
+        self
+    }
+}
+
+impl Spans {
+    // every span, pre-order, w/ its depth, its own index in that order and
+    // its parent's
+    fn walk(&self, mut visit: impl FnMut(&Span, usize, usize, Option<usize>)) {
+        fn go(
+            spans: &[Span],
+            depth: usize,
+            parent: Option<usize>,
+            next_id: &mut usize,
+            visit: &mut impl FnMut(&Span, usize, usize, Option<usize>),
+        ) {
+            for span in spans {
+                let id = *next_id;
+                *next_id += 1;
+                visit(span, depth, id, parent);
+                go(&span.children, depth + 1, Some(id), next_id, visit);
+            }
+        }
+        go(&self.0, 0, None, &mut 0, &mut visit);
+    }
+
+    // one line per span, children indented under their parent:
+    //   name start..end (duration) key="value" ...
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        self.walk(|span, depth, _, _| {
+            write!(
+                out,
+                "{:indent$}{} {:?}..{:?} ({:?})",
+                "",
+                span.name,
+                span.start,
+                span.end,
+                span.duration(),
+                indent = depth * 2
+            )
+            .unwrap();
+            for (k, v) in &span.fields {
+                write!(out, " {}={:?}", k, v).unwrap();
+            }
+            out.push('\n');
+        });
+        out
+    }
+
+    // one JSON object per line, times in nanoseconds; `parent` is the `id`
+    // (pre-order index) of the enclosing span, or null
+    pub fn to_json_lines(&self) -> String {
+        let mut out = String::new();
+        self.walk(|span, depth, id, parent| {
+            let fields: Vec<String> = span
+                .fields
+                .iter()
+                .map(|(k, v)| format!("{}:{}", json_string(k), json_string(v)))
+                .collect();
+            writeln!(
+                out,
+                concat!(
+                    r#"{{"id":{},"parent":{},"depth":{},"name":{},"#,
+                    r#""start_ns":{},"end_ns":{},"duration_ns":{},"fields":{{{}}}}}"#
+                ),
+                id,
+                parent.map_or("null".to_string(), |p| p.to_string()),
+                depth,
+                json_string(&span.name),
+                span.start.as_nanos(),
+                span.end.as_nanos(),
+                span.duration().as_nanos(),
+                fields.join(","),
+            )
+            .unwrap();
+        });
+        out
+    }
+}
+
+impl fmt::Display for Spans {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render_text())
+    }
+}
+
+// makes spans, reading the time off its clock; clones share the clock
+#[derive(Clone)]
+pub struct Tracer {
+    clock: Rc<dyn Clock>,
+}
+
+impl Tracer {
+    pub fn new(clock: impl Clock + 'static) -> Self {
+        Self {
+            clock: Rc::new(clock),
+        }
+    }
+
+    // time `f`, logging a span named `name` w/ f's own spans as children
+    pub fn span<T>(&self, name: impl Into<String>, f: impl FnOnce() -> Traced<T>) -> Traced<T> {
+        let start = self.clock.now();
+        let Writer(v, Spans(children)) = f();
+        let end = self.clock.now();
+        let span = Span {
+            name: name.into(),
+            start,
+            end,
+            fields: Vec::new(),
+            children,
+        };
+        Writer(v, Spans(vec![span]))
+    }
+
+    // a plain function as a traced pipeline stage
+    pub fn stage<A, B>(
+        &self,
+        name: impl Into<String>,
+        f: impl Fn(A) -> B,
+    ) -> impl Fn(A) -> Traced<B> {
+        let (tracer, name) = (self.clone(), name.into());
+        move |a| tracer.span(name.as_str(), || Writer(f(a), Spans::empty()))
+    }
+
+    // a ch4-style String-logging function (e.g. `upcase`) as a traced stage,
+    // keeping its log as the span's `log` field
+    pub fn lift<A, B>(
+        &self,
+        name: impl Into<String>,
+        f: impl Fn(A) -> Writer<B>,
+    ) -> impl Fn(A) -> Traced<B> {
+        let (tracer, name) = (self.clone(), name.into());
+        move |a| {
+            let mut log = String::new();
+            let traced = tracer.span(name.as_str(), || {
+                let Writer(b, s) = f(a);
+                log = s;
+                Writer(b, Spans::empty())
+            });
+            traced.field("log", log)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ch4::writer::{compose, identity_morphism, to_words, upcase};
+    use crate::memoize_eviction::ManualClock;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    // `f`, but taking `cost` on the clock
+    fn slow<A, B>(clock: &ManualClock, cost: Duration, f: impl Fn(A) -> B) -> impl Fn(A) -> B {
+        let clock = clock.clone();
+        move |a| {
+            clock.advance(cost);
+            f(a)
+        }
+    }
+
+    // upcase >=> to_words >=> count, all traced, inside a "pipeline" span
+    fn traced_pipeline(clock: &ManualClock) -> impl Fn(String) -> Traced<usize> {
+        let tracer = Tracer::new(clock.clone());
+        let stages = compose(
+            compose(
+                tracer.lift("upcase", slow(clock, ms(5), upcase)),
+                tracer.lift("to_words", slow(clock, ms(10), to_words)),
+            ),
+            {
+                let count = tracer.stage("count", slow(clock, ms(1), |ws: Vec<String>| ws.len()));
+                move |ws: Vec<String>| {
+                    let n = ws.len();
+                    count(ws).field("words", n)
+                }
+            },
+        );
+        move |s| tracer.span("pipeline", || stages(s))
+    }
+
+    #[test]
+    fn test_spans_nest_and_time() {
+        let clock = ManualClock::new();
+        clock.set(ms(100));
+        let Writer(n, Spans(spans)) = traced_pipeline(&clock)("a few words".to_string());
+
+        assert_eq!(n, 3);
+        assert_eq!(spans.len(), 1);
+        let pipeline = &spans[0];
+        assert_eq!((pipeline.start, pipeline.end), (ms(100), ms(116)));
+        let names: Vec<&str> = pipeline.children.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["upcase", "to_words", "count"]);
+        let durations: Vec<Duration> = pipeline.children.iter().map(Span::duration).collect();
+        assert_eq!(durations, [ms(5), ms(10), ms(1)]);
+        assert_eq!(
+            pipeline.children[0].fields,
+            [("log".to_string(), "upcase ".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_render_text() {
+        let clock = ManualClock::new();
+        let Writer(_, spans) = traced_pipeline(&clock)("hi there".to_string());
+        assert_eq!(
+            spans.render_text(),
+            concat!(
+                "pipeline 0ns..16ms (16ms)\n",
+                "  upcase 0ns..5ms (5ms) log=\"upcase \"\n",
+                "  to_words 5ms..15ms (10ms) log=\"to_words \"\n",
+                "  count 15ms..16ms (1ms) words=\"2\"\n",
+            )
+        );
+        assert_eq!(spans.to_string(), spans.render_text());
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let clock = ManualClock::new();
+        let Writer(_, spans) = traced_pipeline(&clock)("hi".to_string());
+        let json = spans.to_json_lines();
+        let lines: Vec<&str> = json.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            concat!(
+                r#"{"id":0,"parent":null,"depth":0,"name":"pipeline","#,
+                r#""start_ns":0,"end_ns":16000000,"duration_ns":16000000,"fields":{}}"#
+            )
+        );
+        assert_eq!(
+            lines[3],
+            concat!(
+                r#"{"id":3,"parent":0,"depth":1,"name":"count","#,
+                r#""start_ns":15000000,"end_ns":16000000,"duration_ns":1000000,"#,
+                r#""fields":{"words":"1"}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_json_escaping() {
+        let tracer = Tracer::new(ManualClock::new());
+        let Writer((), spans) = tracer
+            .span("say \"hi\"", || Writer((), Spans::empty()))
+            .field("path", "a\\b\n");
+        assert_eq!(
+            spans.to_json_lines(),
+            concat!(
+                r#"{"id":0,"parent":null,"depth":0,"name":"say \"hi\"","start_ns":0,"#,
+                r#""end_ns":0,"duration_ns":0,"fields":{"path":"a\\b\n"}}"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_field_on_an_empty_log() {
+        let untraced: Traced<u8> = identity_morphism(7).field("ignored", true);
+        assert_eq!(untraced, Writer(7, Spans::empty()));
+
+        // inside a span, nothing has been logged yet either: the field is
+        // dropped rather than landing on the enclosing span
+        let tracer = Tracer::new(ManualClock::new());
+        let Writer(n, spans) = tracer
+            .span("outer", || identity_morphism(1u8).field("dropped", 0))
+            .field("kept", 1);
+        assert_eq!(n, 1);
+        assert_eq!(spans.0[0].fields, [("kept".to_string(), "1".to_string())]);
+        assert!(spans.0[0].children.is_empty());
+    }
+
+    #[test]
+    fn test_sibling_pipelines_combine() {
+        let clock = ManualClock::new();
+        let pipeline = traced_pipeline(&clock);
+        let Writer(total, spans) = pipeline("one".to_string())
+            .and_then(|a| pipeline("two three".to_string()).map(|b| a + b));
+        assert_eq!(total, 3);
+        assert_eq!(spans.0.len(), 2);
+        assert_eq!(spans.0[1].start, ms(16));
+        assert_eq!(spans.to_json_lines().lines().count(), 8);
+    }
+}